@@ -0,0 +1,131 @@
+use std::convert::{TryFrom, TryInto};
+
+use anyhow::{anyhow, Result};
+use holo_hash::DnaHash;
+use holochain_serialized_bytes::prelude::*;
+use holochain_types::app::InstalledCell;
+use holochain_zome_types::CellId;
+
+use crate::{
+    client::CompositoryClient,
+    conductor_api::{app_websocket::AppWebsocket, types::ClientAppResponse},
+    error::CompositoryError,
+};
+
+/// Zomes that the compository DNA must expose for the CLI to be able to publish into it
+const COMPOSITORY_ZOMES: [&str; 2] = ["compository", "file_storage"];
+
+pub async fn get_compository_cell_id(
+    ws: &mut AppWebsocket,
+    installed_app_id: String,
+    compository_dna_hash: Option<DnaHash>,
+) -> Result<CellId> {
+    let app_info = ws.app_info(installed_app_id.clone()).await?;
+
+    match app_info {
+        ClientAppResponse::AppInfo(Some(info)) => match compository_dna_hash {
+            Some(dna_hash) => find_cell_for_dna(&dna_hash, info.cell_data).map(|c| c.into_id()),
+            None => discover_compository_cell(ws, info.cell_data)
+                .await
+                .map(|c| c.into_id()),
+        },
+        ClientAppResponse::AppInfo(None) => {
+            Err(CompositoryError::MissingApp(installed_app_id).into())
+        }
+        other => Err(CompositoryError::unexpected_response("AppInfo", &other).into()),
+    }
+}
+
+pub fn parse_dna_hash(dna_hash: &str) -> Result<DnaHash> {
+    DnaHash::try_from(dna_hash).map_err(|e| anyhow!("Invalid dna hash {}: {:?}", dna_hash, e))
+}
+
+fn find_cell_for_dna(dna_hash: &DnaHash, cells: Vec<InstalledCell>) -> Result<InstalledCell> {
+    let available_dna_hashes: Vec<String> = cells
+        .iter()
+        .map(|cell| format!("{}", cell.as_id().dna_hash()))
+        .collect();
+
+    let maybe_compository_cell = cells
+        .into_iter()
+        .find(|cell| cell.as_id().dna_hash() == dna_hash);
+
+    maybe_compository_cell.ok_or(
+        CompositoryError::MissingCell(format!(
+            "Could not find dna {} in this installed app, available dnas are: [{}]",
+            dna_hash,
+            available_dna_hashes.join(", ")
+        ))
+        .into(),
+    )
+}
+
+/// Picks the only cell of the app, or the only cell whose DNA exposes all the `COMPOSITORY_ZOMES`
+async fn discover_compository_cell(
+    ws: &mut AppWebsocket,
+    cells: Vec<InstalledCell>,
+) -> Result<InstalledCell> {
+    if cells.len() == 1 {
+        let cell = cells.into_iter().next().unwrap();
+        eprintln!(
+            "Using the only cell of the app: {} ({})",
+            cell.as_nick(),
+            cell.as_id().dna_hash()
+        );
+        return Ok(cell);
+    }
+
+    let mut compository_cells: Vec<InstalledCell> = vec![];
+    for cell in cells.iter() {
+        let client = CompositoryClient::new(ws.clone(), cell.as_id().clone());
+        if exposes_compository_zomes(&client).await? {
+            compository_cells.push(cell.clone());
+        }
+    }
+
+    if compository_cells.len() == 1 {
+        let cell = compository_cells.into_iter().next().unwrap();
+        eprintln!(
+            "Using the only cell with the {} zomes: {} ({})",
+            COMPOSITORY_ZOMES.join(" and "),
+            cell.as_nick(),
+            cell.as_id().dna_hash()
+        );
+        return Ok(cell);
+    }
+
+    let cells_list: Vec<String> = cells
+        .iter()
+        .map(|cell| format!("  {}: {}", cell.as_nick(), cell.as_id().dna_hash()))
+        .collect();
+
+    Err(CompositoryError::MissingCell(format!(
+        "Could not choose the compository cell automatically, {} cells expose the {} zomes. Pass the dna hash with -c, the cells of this app are:\n{}",
+        compository_cells.len(),
+        COMPOSITORY_ZOMES.join(" and "),
+        cells_list.join("\n")
+    ))
+    .into())
+}
+
+/// Calls the `entry_defs` callback, which every zome exports, to check whether the zomes exist in the cell.
+/// Only a missing zome or function means the cell is not a compository, any other failure is returned
+async fn exposes_compository_zomes(client: &CompositoryClient) -> Result<bool> {
+    for zome_name in COMPOSITORY_ZOMES.iter() {
+        let payload: SerializedBytes = ().try_into()?;
+
+        if let Err(error) = client.call_raw(zome_name, "entry_defs", payload).await {
+            let is_missing = error
+                .downcast_ref::<CompositoryError>()
+                .map(|compository_error| compository_error.is_missing_zome_or_fn())
+                .unwrap_or(false);
+
+            match is_missing {
+                true => return Ok(false),
+                false => return Err(error),
+            }
+        }
+    }
+
+    Ok(true)
+}
@@ -0,0 +1,194 @@
+use std::convert::{TryFrom, TryInto};
+
+use anyhow::Result;
+use hc_utils::WrappedEntryHash;
+use holo_hash::EntryHash;
+use holochain_serialized_bytes::prelude::*;
+use holochain_zome_types::CellId;
+
+use crate::{
+    conductor_api::{
+        app_websocket::{AppWebsocket, SignalReceiver},
+        types::{ClientAppResponse, ClientZomeCall},
+    },
+    error::{decode, CompositoryError},
+    types::{
+        Chunk, CreateFileMetadataInput, DnaTemplate, FileMetadata, GetAllDnaTemplatesOutput,
+        GetAllZomeDefsOutput, PublishInstantiatedDnaInput, ZomeToPublish,
+    },
+};
+
+/// Typed client for the `compository` and `file_storage` zomes of a compository cell
+#[derive(Clone)]
+pub struct CompositoryClient {
+    ws: AppWebsocket,
+    cell_id: CellId,
+}
+
+impl CompositoryClient {
+    pub fn new(ws: AppWebsocket, cell_id: CellId) -> Self {
+        CompositoryClient { ws, cell_id }
+    }
+
+    pub fn cell_id(&self) -> &CellId {
+        &self.cell_id
+    }
+
+    /// Biggest message, in bytes, that can be sent to the conductor
+    pub fn max_message_size(&self) -> usize {
+        self.ws.max_message_size()
+    }
+
+    pub fn subscribe_signals(&self) -> SignalReceiver {
+        self.ws.subscribe_signals()
+    }
+
+    /// Builds the zome call for this cell, with the agent of the cell as provenance
+    pub fn zome_call(
+        &self,
+        zome_name: &str,
+        fn_name: &str,
+        payload: SerializedBytes,
+    ) -> ClientZomeCall {
+        ClientZomeCall {
+            cap: None,
+            cell_id: self.cell_id.clone(),
+            fn_name: fn_name.into(),
+            payload,
+            provenance: self.cell_id.agent_pubkey().clone(),
+            zome_name: zome_name.into(),
+        }
+    }
+
+    /// Calls the zome function with an already serialized payload, returning the raw response
+    pub async fn call_raw(
+        &self,
+        zome_name: &str,
+        fn_name: &str,
+        payload: SerializedBytes,
+    ) -> Result<SerializedBytes> {
+        let zome_call = self.zome_call(zome_name, fn_name, payload);
+
+        let response = self.ws.clone().call_zome(&self.cell_id, zome_call).await?;
+
+        match response {
+            ClientAppResponse::ZomeCall(bytes) => Ok(bytes),
+            other => Err(CompositoryError::unexpected_response("ZomeCall", &other).into()),
+        }
+    }
+
+    pub async fn call<I, O>(&self, zome_name: &str, fn_name: &str, input: I) -> Result<O>
+    where
+        I: TryInto<SerializedBytes, Error = SerializedBytesError>,
+        O: TryFrom<SerializedBytes, Error = SerializedBytesError>,
+    {
+        let bytes = self.call_raw(zome_name, fn_name, input.try_into()?).await?;
+
+        Ok(decode(
+            bytes,
+            &format!("the response of {}/{}", zome_name, fn_name),
+        )?)
+    }
+
+    /** compository zome */
+
+    pub async fn publish_zome(&self, zome_to_publish: ZomeToPublish) -> Result<String> {
+        self.call_for_hash("compository", "publish_zome", zome_to_publish)
+            .await
+    }
+
+    pub async fn publish_dna_template(&self, dna_template: DnaTemplate) -> Result<String> {
+        self.call_for_hash("compository", "publish_dna_template", dna_template)
+            .await
+    }
+
+    pub async fn publish_instantiated_dna(&self, input: PublishInstantiatedDnaInput) -> Result<()> {
+        self.call_raw("compository", "publish_instantiated_dna", input.try_into()?)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_zome_def(&self, zome_def_hash: &str) -> Result<ZomeToPublish> {
+        self.call(
+            "compository",
+            "get_zome_def",
+            parse_entry_hash(zome_def_hash)?,
+        )
+        .await
+    }
+
+    pub async fn get_dna_template(&self, dna_template_hash: &str) -> Result<DnaTemplate> {
+        self.call(
+            "compository",
+            "get_dna_template",
+            parse_entry_hash(dna_template_hash)?,
+        )
+        .await
+    }
+
+    pub async fn get_all_zome_defs(&self) -> Result<GetAllZomeDefsOutput> {
+        self.call("compository", "get_all_zome_defs", ()).await
+    }
+
+    pub async fn get_all_dna_templates(&self) -> Result<GetAllDnaTemplatesOutput> {
+        self.call("compository", "get_all_dna_templates", ()).await
+    }
+
+    /** file_storage zome */
+
+    pub async fn create_file_chunk(&self, chunk: Chunk) -> Result<String> {
+        self.call_for_hash("file_storage", "create_file_chunk", chunk)
+            .await
+    }
+
+    pub async fn create_file_metadata(&self, input: CreateFileMetadataInput) -> Result<String> {
+        self.call_for_hash("file_storage", "create_file_metadata", input)
+            .await
+    }
+
+    pub async fn get_file_metadata(&self, file_hash: &str) -> Result<FileMetadata> {
+        self.call(
+            "file_storage",
+            "get_file_metadata",
+            parse_entry_hash(file_hash)?,
+        )
+        .await
+    }
+
+    pub async fn get_file_chunk(&self, chunk_hash: &str) -> Result<Chunk> {
+        self.call(
+            "file_storage",
+            "get_file_chunk",
+            parse_entry_hash(chunk_hash)?,
+        )
+        .await
+    }
+
+    /** Helper functions */
+
+    /// Calls a function that creates an entry, returning its hash encoded as a string
+    async fn call_for_hash<I>(&self, zome_name: &str, fn_name: &str, input: I) -> Result<String>
+    where
+        I: TryInto<SerializedBytes, Error = SerializedBytesError>,
+    {
+        let hash: WrappedEntryHash = self.call(zome_name, fn_name, input).await?;
+
+        Ok(format!("{}", hash.0))
+    }
+}
+
+fn parse_entry_hash(entry_hash: &str) -> Result<WrappedEntryHash> {
+    let hash = EntryHash::try_from(entry_hash).map_err(|e| {
+        CompositoryError::InvalidInput(format!("Invalid entry hash {}: {:?}", entry_hash, e))
+    })?;
+
+    Ok(WrappedEntryHash(hash))
+}
+
+/// Validates a zome def or DNA template hash given as an argument, before connecting to the conductor
+pub fn check_entry_hash(entry_hash: &str) -> Result<String> {
+    parse_entry_hash(entry_hash)?;
+
+    Ok(entry_hash.into())
+}
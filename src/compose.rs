@@ -0,0 +1,71 @@
+use std::{collections::BTreeMap, path::Path, path::PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use holochain_zome_types::zome::ZomeName;
+
+use crate::{
+    client::CompositoryClient,
+    dna_file::{check_zome_name, DnaDefJson, ZomeJson},
+    fetch::{fetch_zome, write_zome},
+};
+
+/// Downloads the given published zomes into a new `<dna name>.dna.workdir` inside the output directory,
+/// with a `dna.json` that references them, ready to be read by `read_dna` and published again
+pub async fn compose_dna(
+    client: &CompositoryClient,
+    dna_name: String,
+    zome_def_hashes: Vec<String>,
+    output_dir: &Path,
+) -> Result<PathBuf> {
+    let workdir = output_dir.join(format!("{}.dna.workdir", dna_name));
+    let dna_json_path = workdir.join("dna.json");
+    if dna_json_path.exists() {
+        return Err(anyhow!(
+            "{} already exists, remove it or choose another output directory",
+            dna_json_path.display()
+        ));
+    }
+
+    let mut zomes: BTreeMap<ZomeName, ZomeJson> = BTreeMap::new();
+
+    for zome_def_hash in zome_def_hashes {
+        let fetched_zome = fetch_zome(client, zome_def_hash.clone())
+            .await
+            .with_context(|| format!("Failed to fetch zome {}", zome_def_hash))?;
+        let zome_name = fetched_zome.zome_def.name.clone();
+        // The name is used for the file names in the workdir and in the dna.json
+        check_zome_name(&zome_name)?;
+
+        if zomes.contains_key(&ZomeName::from(zome_name.clone())) {
+            return Err(anyhow!(
+                "There are two zomes named {}, a DNA can only have one zome with each name",
+                zome_name
+            ));
+        }
+
+        write_zome(&fetched_zome, &workdir).await?;
+
+        // Same file names as `write_zome`, relative to the workdir
+        let zome_json = ZomeJson {
+            wasm_path: format!("./{}.wasm", zome_name),
+            ui_path: fetched_zome
+                .components_bundle
+                .as_ref()
+                .map(|_| format!("./{}.bundle.js", zome_name)),
+        };
+        zomes.insert(zome_name.into(), zome_json);
+    }
+
+    let dna_def_json = DnaDefJson {
+        name: dna_name,
+        uuid: "".into(),
+        properties: serde_json::Value::Null,
+        zomes,
+        ui_path: None,
+    };
+
+    tokio::fs::write(&dna_json_path, serde_json::to_vec_pretty(&dna_def_json)?).await?;
+    println!("Wrote {}", dna_json_path.display());
+
+    Ok(workdir)
+}
@@ -0,0 +1,203 @@
+use anyhow::{Context, Result};
+use holo_hash::{AgentPubKey, DnaHash};
+use holochain_types::{
+    app::{InstallAppDnaPayload, InstallAppPayload, InstalledApp, InstalledAppId},
+    dna::DnaFile,
+};
+use holochain_websocket::{websocket_connect, WebsocketConfig, WebsocketSender};
+use holochain_zome_types::CellId;
+use std::{path::PathBuf, sync::Arc};
+use tracing::{instrument, trace};
+use url::Url;
+
+use super::{
+    app_websocket::RequestConfig,
+    types::{ClientAdminRequest, ClientAdminResponse},
+};
+use crate::error::CompositoryError;
+
+/// Client for the Admin interface of the conductor, used to install and run the compository app.
+/// None of its requests are retried, as most of them change the state of the conductor
+#[derive(Clone)]
+pub struct AdminWebsocket {
+    url: Url,
+    tx: WebsocketSender,
+    request_config: RequestConfig,
+}
+
+impl AdminWebsocket {
+    #[instrument(err)]
+    pub async fn connect(url: String, request_config: RequestConfig) -> Result<Self> {
+        let url = Url::parse(&url).map_err(|error| {
+            CompositoryError::InvalidInput(format!("invalid ws:// URL {}: {}", url, error))
+        })?;
+        let websocket_config = Arc::new(WebsocketConfig::default());
+        // Admin interfaces don't emit signals, so the receiver is dropped
+        let (tx, _rx) = websocket_connect(url.clone().into(), websocket_config)
+            .await
+            .map_err(|error| {
+                CompositoryError::Connection(format!("could not connect to {}: {:?}", url, error))
+            })?;
+
+        Ok(Self {
+            url,
+            tx,
+            request_config,
+        })
+    }
+
+    /// URL of the App interface on the port, in the same host as this Admin interface
+    pub fn app_interface_url(&self, port: u16) -> String {
+        format!(
+            "ws://{}:{}",
+            self.url.host_str().unwrap_or("localhost"),
+            port
+        )
+    }
+
+    pub fn request_config(&self) -> RequestConfig {
+        self.request_config.clone()
+    }
+
+    #[instrument(skip(self), err)]
+    pub async fn generate_agent_pub_key(&mut self) -> Result<AgentPubKey> {
+        match self.send(ClientAdminRequest::GenerateAgentPubKey).await? {
+            ClientAdminResponse::AgentPubKeyGenerated(agent_key) => Ok(agent_key),
+            other => {
+                Err(CompositoryError::unexpected_response("AgentPubKeyGenerated", &other).into())
+            }
+        }
+    }
+
+    /// Installs the DNA file found at the path, which must be readable by the conductor
+    #[instrument(skip(self, agent_key), err)]
+    pub async fn install_app_from_path(
+        &mut self,
+        installed_app_id: String,
+        agent_key: AgentPubKey,
+        nick: String,
+        path: PathBuf,
+    ) -> Result<InstalledApp> {
+        let payload = InstallAppPayload {
+            installed_app_id,
+            agent_key,
+            dnas: vec![InstallAppDnaPayload {
+                path,
+                nick,
+                properties: None,
+                membrane_proof: None,
+            }],
+        };
+
+        match self
+            .send(ClientAdminRequest::InstallApp(Box::new(payload)))
+            .await?
+        {
+            ClientAdminResponse::AppInstalled(installed_app) => Ok(installed_app),
+            other => Err(CompositoryError::unexpected_response("AppInstalled", &other).into()),
+        }
+    }
+
+    /// Writes the DNA file to the temporary directory and installs it from there,
+    /// so the conductor must run in the same machine. The file is removed once the conductor answers
+    #[instrument(skip(self, agent_key, dna_file), err)]
+    pub async fn install_app(
+        &mut self,
+        installed_app_id: String,
+        agent_key: AgentPubKey,
+        nick: String,
+        dna_file: &DnaFile,
+    ) -> Result<InstalledApp> {
+        let path = std::env::temp_dir().join(format!("{}.dna.gz", dna_file.dna_hash()));
+        tokio::fs::write(&path, dna_file.to_file_content().await?)
+            .await
+            .with_context(|| format!("Failed to write the DNA file to {}", path.display()))?;
+
+        let result = self
+            .install_app_from_path(installed_app_id, agent_key, nick, path.clone())
+            .await;
+        let removed = tokio::fs::remove_file(&path).await;
+
+        // A failed install is more relevant than a leftover temporary file
+        let installed_app = result?;
+        removed.with_context(|| format!("Failed to remove the DNA file {}", path.display()))?;
+
+        Ok(installed_app)
+    }
+
+    #[instrument(skip(self), err)]
+    pub async fn activate_app(&mut self, installed_app_id: String) -> Result<()> {
+        match self
+            .send(ClientAdminRequest::ActivateApp { installed_app_id })
+            .await?
+        {
+            ClientAdminResponse::AppActivated => Ok(()),
+            other => Err(CompositoryError::unexpected_response("AppActivated", &other).into()),
+        }
+    }
+
+    /// Opens an App interface on the port, or on any free port if `None`, and returns its port
+    #[instrument(skip(self), err)]
+    pub async fn attach_app_interface(&mut self, port: Option<u16>) -> Result<u16> {
+        match self
+            .send(ClientAdminRequest::AttachAppInterface { port })
+            .await?
+        {
+            ClientAdminResponse::AppInterfaceAttached { port } => Ok(port),
+            other => {
+                Err(CompositoryError::unexpected_response("AppInterfaceAttached", &other).into())
+            }
+        }
+    }
+
+    #[instrument(skip(self), err)]
+    pub async fn list_dnas(&mut self) -> Result<Vec<DnaHash>> {
+        match self.send(ClientAdminRequest::ListDnas).await? {
+            ClientAdminResponse::DnasListed(dnas) => Ok(dnas),
+            other => Err(CompositoryError::unexpected_response("DnasListed", &other).into()),
+        }
+    }
+
+    #[instrument(skip(self), err)]
+    pub async fn list_cell_ids(&mut self) -> Result<Vec<CellId>> {
+        match self.send(ClientAdminRequest::ListCellIds).await? {
+            ClientAdminResponse::CellIdsListed(cell_ids) => Ok(cell_ids),
+            other => Err(CompositoryError::unexpected_response("CellIdsListed", &other).into()),
+        }
+    }
+
+    #[instrument(skip(self), err)]
+    pub async fn list_active_app_ids(&mut self) -> Result<Vec<InstalledAppId>> {
+        match self.send(ClientAdminRequest::ListActiveAppIds).await? {
+            ClientAdminResponse::ActiveAppIdsListed(app_ids) => Ok(app_ids),
+            other => {
+                Err(CompositoryError::unexpected_response("ActiveAppIdsListed", &other).into())
+            }
+        }
+    }
+
+    /// Sends the request once, failing if there's no response within the configured timeout
+    async fn send(&mut self, msg: ClientAdminRequest) -> Result<ClientAdminResponse> {
+        let timeout = self.request_config.timeout;
+
+        let response: ClientAdminResponse = tokio::time::timeout(timeout, self.tx.request(msg))
+            .await
+            .map_err(|_| {
+                CompositoryError::Connection(format!(
+                    "no response from the conductor at {} after {:?}",
+                    self.url, timeout
+                ))
+            })?
+            .map_err(|error| {
+                CompositoryError::Connection(format!("failed to send message: {:?}", error))
+            })?;
+
+        match response {
+            ClientAdminResponse::Error(error) => Err(CompositoryError::from(error).into()),
+            _ => {
+                trace!("send successful");
+                Ok(response)
+            }
+        }
+    }
+}
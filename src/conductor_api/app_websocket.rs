@@ -1,53 +1,272 @@
-use anyhow::{anyhow, Context, Result};
-use holochain_websocket::{websocket_connect, WebsocketConfig, WebsocketSender};
+use anyhow::Result;
+use futures::StreamExt;
+use holochain_serialized_bytes::prelude::*;
+use holochain_websocket::{
+    websocket_connect, WebsocketConfig, WebsocketMessage, WebsocketReceiver, WebsocketSender,
+};
 use holochain_zome_types::CellId;
-use std::sync::Arc;
-use tracing::{instrument, trace};
+use std::{convert::TryFrom, sync::Arc, time::Duration};
+use tokio::sync::{broadcast, RwLock};
+use tracing::{info, instrument, trace, warn};
 use url::Url;
 
-use super::{types::{ClientAppRequest, ClientAppResponse, ClientZomeCall}};
+use super::types::{ClientAppRequest, ClientAppResponse, ClientZomeCall, Signal};
+use crate::error::CompositoryError;
+
+/// Signals buffered for each subscriber before the oldest ones are dropped
+const SIGNALS_BUFFER_SIZE: usize = 100;
+
+/// Zome functions that can be called again without side effects if a previous attempt failed
+const RETRIABLE_ZOME_FNS: [&str; 2] = ["entry_defs", "create_file_chunk"];
+
+/// How long to wait for each request, and how to retry the ones that can be retried
+#[derive(Debug, Clone)]
+pub struct RequestConfig {
+    pub timeout: Duration,
+    pub retries: usize,
+    /// Delay before the first retry, doubled in every following one
+    pub initial_backoff: Duration,
+}
+
+impl Default for RequestConfig {
+    fn default() -> Self {
+        RequestConfig {
+            timeout: Duration::from_secs(60),
+            retries: 3,
+            initial_backoff: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Connection shared by all the clones of an `AppWebsocket`, replaced when it gets closed
+struct Connection {
+    tx: WebsocketSender,
+    /// Number of times the connection has been reestablished
+    generation: usize,
+}
+
+#[derive(Clone, Debug)]
+enum SignalEvent {
+    App(CellId, SerializedBytes),
+    /// The connection was closed and could not be reestablished
+    Disconnected,
+}
+
+/// App signals received through an `AppWebsocket`, across reconnections
+pub struct SignalReceiver {
+    rx: broadcast::Receiver<SignalEvent>,
+}
+
+impl SignalReceiver {
+    /// Waits for the next app signal, failing if the connection is lost for good
+    pub async fn recv(&mut self) -> Result<(CellId, SerializedBytes)> {
+        loop {
+            match self.rx.recv().await {
+                Ok(SignalEvent::App(cell_id, payload)) => return Ok((cell_id, payload)),
+                Ok(SignalEvent::Disconnected) | Err(broadcast::RecvError::Closed) => {
+                    return Err(CompositoryError::Connection(
+                        "the connection to the conductor was lost".into(),
+                    )
+                    .into())
+                }
+                Err(broadcast::RecvError::Lagged(skipped)) => {
+                    warn!("skipped {} signals that were not read in time", skipped)
+                }
+            }
+        }
+    }
+}
 
 #[derive(Clone)]
 pub struct AppWebsocket {
-    tx: WebsocketSender,
+    url: Url,
+    connection: Arc<RwLock<Connection>>,
+    websocket_config: Arc<WebsocketConfig>,
+    request_config: RequestConfig,
+    signals_tx: broadcast::Sender<SignalEvent>,
 }
 
 impl AppWebsocket {
     #[instrument(err)]
-    pub async fn connect(url: String) -> Result<Self> {
-        let url = Url::parse(&url).context("invalid ws:// URL")?;
+    pub async fn connect(url: String, request_config: RequestConfig) -> Result<Self> {
+        let url = Url::parse(&url).map_err(|error| {
+            CompositoryError::InvalidInput(format!("invalid ws:// URL {}: {}", url, error))
+        })?;
         let websocket_config = Arc::new(WebsocketConfig::default());
-        let (tx, _rx) = websocket_connect(url.clone().into(), websocket_config).await?;
-        Ok(Self { tx })
+        let (tx, rx) = websocket_connect(url.clone().into(), websocket_config.clone())
+            .await
+            .map_err(|error| {
+                CompositoryError::Connection(format!("could not connect to {}: {:?}", url, error))
+            })?;
+        let (signals_tx, _) = broadcast::channel(SIGNALS_BUFFER_SIZE);
+
+        let ws = Self {
+            url,
+            connection: Arc::new(RwLock::new(Connection { tx, generation: 0 })),
+            websocket_config,
+            request_config,
+            signals_tx,
+        };
+        ws.spawn_signal_forwarder(rx, 0);
+
+        Ok(ws)
+    }
+
+    /// Receives the app signals of all cells from now on
+    pub fn subscribe_signals(&self) -> SignalReceiver {
+        SignalReceiver {
+            rx: self.signals_tx.subscribe(),
+        }
+    }
+
+    /// Biggest message, in bytes, that can be sent through this websocket
+    pub fn max_message_size(&self) -> usize {
+        self.websocket_config
+            .max_message_size
+            .min(self.websocket_config.max_frame_size)
     }
 
     #[instrument(skip(self), err)]
     pub async fn app_info(&mut self, installed_app_id: String) -> Result<ClientAppResponse> {
-        self.send(ClientAppRequest::AppInfo { installed_app_id })
+        self.send(ClientAppRequest::AppInfo { installed_app_id }, true)
             .await
     }
 
+    /// Getters and the zome functions in `RETRIABLE_ZOME_FNS` are retried on transient failures.
+    /// Other calls are not, as they may have been applied even if their response was lost
     #[instrument(skip(self), err)]
     pub async fn call_zome(
         &mut self,
         cell_id: &CellId,
         call: ClientZomeCall,
     ) -> Result<ClientAppResponse> {
-        self.send(ClientAppRequest::ZomeCall(call)).await
+        let fn_name = call.fn_name.0.as_str();
+        let retriable = fn_name.starts_with("get_") || RETRIABLE_ZOME_FNS.contains(&fn_name);
+
+        self.send(ClientAppRequest::ZomeCall(call), retriable).await
     }
 
-    async fn send(&mut self, msg: ClientAppRequest) -> Result<ClientAppResponse> {
-        let response = self
-            .tx
-            .request(msg)
-            .await
-            .context("failed to send message")?;
+    async fn send(&mut self, msg: ClientAppRequest, retriable: bool) -> Result<ClientAppResponse> {
+        let max_attempts = match retriable {
+            true => self.request_config.retries + 1,
+            false => 1,
+        };
+        let mut backoff = self.request_config.initial_backoff;
+        let mut attempt = 1;
+
+        let response = loop {
+            match self.request(msg.clone()).await {
+                Err(error) if attempt < max_attempts => {
+                    warn!(
+                        "request failed ({:?}), retrying in {:?} ({} of {})",
+                        error, backoff, attempt, max_attempts
+                    );
+                    tokio::time::delay_for(backoff).await;
+                    backoff *= 2;
+                    attempt += 1;
+                }
+                result => break result?,
+            }
+        };
+
         match response {
-            ClientAppResponse::Error(error) => Err(anyhow!("error: {:?}", error)),
+            ClientAppResponse::Error(error) => Err(CompositoryError::from(error).into()),
             _ => {
                 trace!("send successful");
                 Ok(response)
             }
         }
     }
+
+    /// Sends the request once, failing if there's no response within the configured timeout.
+    /// If the connection was closed, it's reestablished for the following requests
+    async fn request(&mut self, msg: ClientAppRequest) -> Result<ClientAppResponse> {
+        let timeout = self.request_config.timeout;
+
+        let (mut tx, generation) = {
+            let connection = self.connection.read().await;
+            (connection.tx.clone(), connection.generation)
+        };
+
+        let response = tokio::time::timeout(timeout, tx.request(msg))
+            .await
+            .map_err(|_| {
+                CompositoryError::Connection(format!(
+                    "no response from the conductor after {:?}",
+                    timeout
+                ))
+            })?;
+
+        match response {
+            Ok(response) => Ok(response),
+            Err(error) => {
+                if let Err(reconnect_error) = self.reconnect(generation).await {
+                    warn!("could not reconnect to {}: {:?}", self.url, reconnect_error);
+                }
+                Err(
+                    CompositoryError::Connection(format!("failed to send message: {:?}", error))
+                        .into(),
+                )
+            }
+        }
+    }
+
+    /// Replaces the connection, unless another clone already did it after it failed
+    async fn reconnect(&self, failed_generation: usize) -> Result<()> {
+        let mut connection = self.connection.write().await;
+        if connection.generation != failed_generation {
+            return Ok(());
+        }
+
+        let (tx, rx) =
+            websocket_connect(self.url.clone().into(), self.websocket_config.clone()).await?;
+        *connection = Connection {
+            tx,
+            generation: failed_generation + 1,
+        };
+        self.spawn_signal_forwarder(rx, failed_generation + 1);
+        info!("reconnected to {}", self.url);
+
+        Ok(())
+    }
+
+    /// Forwards the app signals of the connection to the subscribers.
+    /// When the connection is closed, tries to reestablish it with the configured backoff
+    fn spawn_signal_forwarder(&self, mut rx: WebsocketReceiver, generation: usize) {
+        let ws = self.clone();
+
+        tokio::spawn(async move {
+            while let Some(message) = rx.next().await {
+                if let WebsocketMessage::Signal(bytes) = message {
+                    match Signal::try_from(bytes) {
+                        Ok(Signal::App(cell_id, payload)) => {
+                            // Failing only means there are no subscribers right now
+                            let _ = ws.signals_tx.send(SignalEvent::App(cell_id, payload));
+                        }
+                        Err(error) => {
+                            trace!("ignoring signal that is not an app signal: {:?}", error)
+                        }
+                    }
+                }
+            }
+
+            let mut backoff = ws.request_config.initial_backoff;
+            for attempt in 1..=ws.request_config.retries + 1 {
+                match ws.reconnect(generation).await {
+                    Ok(()) => return,
+                    Err(error) => warn!(
+                        "could not reconnect to {} ({} of {}): {:?}",
+                        ws.url,
+                        attempt,
+                        ws.request_config.retries + 1,
+                        error
+                    ),
+                }
+                tokio::time::delay_for(backoff).await;
+                backoff *= 2;
+            }
+
+            let _ = ws.signals_tx.send(SignalEvent::Disconnected);
+        });
+    }
 }
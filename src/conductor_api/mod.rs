@@ -1,2 +1,3 @@
 pub mod types;
 pub mod app_websocket;
+pub mod admin_websocket;
@@ -1,8 +1,12 @@
-use holo_hash::AgentPubKey;
+use holo_hash::{AgentPubKey, DnaHash};
 use holochain::conductor::api::error::ExternalApiWireError;
-use holochain_types::{app::{InstalledApp, InstalledAppId}};
-use holochain_zome_types::{CellId, capability::CapSecret, zome::{FunctionName, ZomeName}};
 use holochain_serialized_bytes::prelude::*;
+use holochain_types::app::{InstallAppPayload, InstalledApp, InstalledAppId};
+use holochain_zome_types::{
+    capability::CapSecret,
+    zome::{FunctionName, ZomeName},
+    CellId,
+};
 
 /// Represents the available Conductor functions to call over an App interface
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize, SerializedBytes)]
@@ -39,7 +43,6 @@ pub struct ClientZomeCall {
     pub provenance: AgentPubKey,
 }
 
-
 /// Responses to requests received on an App interface
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize, SerializedBytes)]
 #[serde(rename_all = "snake_case", tag = "type", content = "data")]
@@ -49,4 +52,85 @@ pub enum ClientAppResponse {
     AppInfo(Option<InstalledApp>),
 
     ZomeCall(SerializedBytes),
-}
\ No newline at end of file
+}
+
+/// Represents the Conductor functions used by this CLI over an Admin interface
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, SerializedBytes)]
+#[serde(rename_all = "snake_case", tag = "type", content = "data")]
+pub enum ClientAdminRequest {
+    /// Installs the DNAs of the payload, creating a cell for each of them with the given agent
+    InstallApp(Box<InstallAppPayload>),
+    /// Lists the hashes of all the installed DNAs
+    ListDnas,
+    /// Generates a new agent public key in the keystore of the conductor
+    GenerateAgentPubKey,
+    /// Lists the ids of all the cells of the conductor
+    ListCellIds,
+    /// Lists the ids of the apps whose cells are running
+    ListActiveAppIds,
+    /// Starts running the cells of an installed app
+    ActivateApp {
+        /// The InstalledAppId to activate
+        installed_app_id: InstalledAppId,
+    },
+    /// Opens a new App interface on the given port, or on a free one if `None`
+    AttachAppInterface { port: Option<u16> },
+}
+
+/// Responses to requests received on an Admin interface
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, SerializedBytes)]
+#[serde(rename_all = "snake_case", tag = "type", content = "data")]
+pub enum ClientAdminResponse {
+    Error(ExternalApiWireError),
+
+    AppInstalled(InstalledApp),
+
+    DnasListed(Vec<DnaHash>),
+
+    AgentPubKeyGenerated(AgentPubKey),
+
+    CellIdsListed(Vec<CellId>),
+
+    ActiveAppIdsListed(Vec<InstalledAppId>),
+
+    AppActivated,
+
+    AppInterfaceAttached { port: u16 },
+}
+
+/// Responses that can be named in the errors for unexpected responses
+pub trait ConductorResponse {
+    fn kind(&self) -> &'static str;
+}
+
+impl ConductorResponse for ClientAppResponse {
+    fn kind(&self) -> &'static str {
+        match self {
+            ClientAppResponse::Error(_) => "Error",
+            ClientAppResponse::AppInfo(_) => "AppInfo",
+            ClientAppResponse::ZomeCall(_) => "ZomeCall",
+        }
+    }
+}
+
+impl ConductorResponse for ClientAdminResponse {
+    fn kind(&self) -> &'static str {
+        match self {
+            ClientAdminResponse::Error(_) => "Error",
+            ClientAdminResponse::AppInstalled(_) => "AppInstalled",
+            ClientAdminResponse::DnasListed(_) => "DnasListed",
+            ClientAdminResponse::AgentPubKeyGenerated(_) => "AgentPubKeyGenerated",
+            ClientAdminResponse::CellIdsListed(_) => "CellIdsListed",
+            ClientAdminResponse::ActiveAppIdsListed(_) => "ActiveAppIdsListed",
+            ClientAdminResponse::AppActivated => "AppActivated",
+            ClientAdminResponse::AppInterfaceAttached { .. } => "AppInterfaceAttached",
+        }
+    }
+}
+
+/// Signals emitted by the conductor to the App interfaces, see `holochain::core::signal::Signal`
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, SerializedBytes)]
+pub enum Signal {
+    /// Signal emitted by a zome, with the id of the cell that runs it
+    App(CellId, SerializedBytes),
+}
@@ -1,6 +1,9 @@
-use std::collections::BTreeMap;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
 use holochain::core::ribosome::{
     guest_callback::entry_defs::{EntryDefsHostAccess, EntryDefsInvocation, EntryDefsResult},
     real_ribosome::RealRibosome,
@@ -16,7 +19,7 @@ use holochain_zome_types::{
     entry_def::{EntryDefId, EntryDefs},
     zome::ZomeName,
 };
-use std::convert::TryInto;
+use std::convert::{TryFrom, TryInto};
 
 use crate::types::ZomeWithCode;
 
@@ -43,14 +46,78 @@ pub fn get_entry_defs(dna_file: DnaFile) -> Result<BTreeMap<ZomeName, EntryDefs>
     }
 }
 
-pub async fn get_zomes(
-    dna_file_content: &DnaDefJson,
-    dna_work_dir: &impl AsRef<std::path::Path>,
-) -> Result<Vec<ZomeWithCode>> {
-    let dna_work_dir = dna_work_dir.as_ref().canonicalize()?;
+/// Where the DNA to publish is read from
+#[derive(Debug, Clone)]
+pub enum DnaSource {
+    /// Workdir with a `dna.json` describing the zomes and their UI bundles
+    Workdir(PathBuf),
+    /// Packed DNA file (.dna.gz), whose zomes are published without UI
+    Packed(PathBuf),
+}
+
+/// DNA read from a `DnaSource`, with the UI bundles of its zomes
+#[derive(Debug, Clone)]
+pub struct LoadedDna {
+    pub name: String,
+    pub dna_file: DnaFile,
+    components_bundles: BTreeMap<ZomeName, Vec<u8>>,
+}
+
+impl DnaSource {
+    pub async fn load(&self) -> Result<LoadedDna> {
+        match self {
+            DnaSource::Workdir(dna_work_dir) => {
+                let dna_work_dir = dna_work_dir.canonicalize()?;
+                let dna_def_json = read_dna(&dna_work_dir).await?;
+
+                let dna_file = dna_def_json.compile_dna_file(&dna_work_dir).await?;
+                let components_bundles =
+                    read_components_bundles(&dna_def_json, &dna_work_dir).await?;
+
+                Ok(LoadedDna {
+                    name: dna_def_json.name,
+                    dna_file,
+                    components_bundles,
+                })
+            }
+            DnaSource::Packed(dna_file_path) => {
+                let dna_file = read_dna_file(dna_file_path).await?;
+
+                Ok(LoadedDna {
+                    name: dna_file.dna.name.clone(),
+                    dna_file,
+                    components_bundles: BTreeMap::new(),
+                })
+            }
+        }
+    }
+}
 
-    let dna_file = dna_file_content.compile_dna_file(&dna_work_dir).await?;
+impl LoadedDna {
+    pub fn zomes(&self) -> Result<Vec<ZomeWithCode>> {
+        get_zomes_from_dna_file(self.dna_file.clone(), self.components_bundles.clone())
+    }
+}
+
+/// Reads a packed DNA file (.dna.gz), as written by `compository pack` or `dna-util`
+pub async fn read_dna_file(dna_file_path: &impl AsRef<std::path::Path>) -> Result<DnaFile> {
+    let dna_file_path = dna_file_path.as_ref();
+    let content = tokio::fs::read(dna_file_path)
+        .await
+        .with_context(|| format!("Failed to read {}", dna_file_path.display()))?;
+
+    let dna_file = DnaFile::from_file_content(&content)
+        .await
+        .with_context(|| format!("{} is not a valid DNA file", dna_file_path.display()))?;
+
+    Ok(dna_file)
+}
 
+/// Extracts the zomes of the DNA with their code and entry defs, attaching the given UI bundles
+pub fn get_zomes_from_dna_file(
+    dna_file: DnaFile,
+    mut components_bundles: BTreeMap<ZomeName, Vec<u8>>,
+) -> Result<Vec<ZomeWithCode>> {
     let (dna_def, _): (DnaDef, Vec<DnaWasm>) = dna_file.clone().into();
     let dna_code = dna_file.code().clone();
 
@@ -73,19 +140,7 @@ pub async fn get_zomes(
             })
             .collect::<Result<Vec<String>>>()?;
 
-        let components_bundle = match dna_file_content.zomes.get(&zome_name) {
-            Some(zome_json) => match zome_json.ui_path.clone() {
-                Some(ui_path) => {
-                    let mut zome_file_path = dna_work_dir.clone();
-                    zome_file_path.push(&ui_path);
-
-                    let file_contents = tokio::fs::read(zome_file_path.clone()).await?;
-                    Some(file_contents)
-                }
-                None => None,
-            },
-            None => None,
-        };
+        let components_bundle = components_bundles.remove(&zome_name);
 
         zomes.push(ZomeWithCode {
             name: zome_name.0,
@@ -101,6 +156,43 @@ pub async fn get_zomes(
     Ok(zomes)
 }
 
+async fn read_components_bundles(
+    dna_file_content: &DnaDefJson,
+    dna_work_dir: &Path,
+) -> Result<BTreeMap<ZomeName, Vec<u8>>> {
+    let mut components_bundles: BTreeMap<ZomeName, Vec<u8>> = BTreeMap::new();
+
+    for (zome_name, zome_json) in dna_file_content.zomes.iter() {
+        if let Some(ui_path) = zome_json.ui_path.clone() {
+            let zome_file_path = dna_work_dir.join(&ui_path);
+
+            let file_contents = tokio::fs::read(zome_file_path.clone()).await?;
+            components_bundles.insert(zome_name.clone(), file_contents);
+        }
+    }
+
+    Ok(components_bundles)
+}
+
+/// Checks that the zome name can be used as a file name inside a directory,
+/// as zome names can come from a remote compository or a third-party DNA file
+pub fn check_zome_name(zome_name: &str) -> Result<()> {
+    let mut components = std::path::Path::new(zome_name).components();
+    let is_file_name = matches!(
+        (components.next(), components.next()),
+        (Some(std::path::Component::Normal(_)), None)
+    );
+
+    if !is_file_name || zome_name.contains('/') || zome_name.contains('\\') {
+        return Err(anyhow!(
+            "Invalid zome name {:?}: it can't contain path separators or be \"..\"",
+            zome_name
+        ));
+    }
+
+    Ok(())
+}
+
 /// See `holochain_types::dna::zome::Zome`.
 /// This is a helper to convert to json.
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
@@ -113,6 +205,14 @@ pub struct ZomeJson {
 #[derive(Debug, serde::Serialize, serde::Deserialize, SerializedBytes)]
 struct JsonValueDecodeHelper(pub serde_json::Value);
 
+/// Decodes the properties of a DNA back to the JSON they were encoded from with `JsonValueDecodeHelper`
+pub fn decode_properties(properties: SerializedBytes) -> Result<serde_json::Value> {
+    let helper = JsonValueDecodeHelper::try_from(properties)
+        .map_err(|e| anyhow!("Could not decode the properties of the dna: {:?}", e))?;
+
+    Ok(helper.0)
+}
+
 /// See `holochain_types::dna::DnaDef`.
 /// This is a helper to convert to json.
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
@@ -131,11 +231,7 @@ impl DnaDefJson {
     ) -> Result<DnaFile> {
         let work_dir = work_dir.into();
 
-        let properties: SerializedBytes =
-            JsonValueDecodeHelper(self.properties.clone()).try_into()?;
-
-        let mut zomes = Vec::new();
-        let mut wasm_list = Vec::new();
+        let mut zome_wasms = Vec::new();
 
         for (zome_name, zome) in self.zomes.iter() {
             let mut zome_file_path = work_dir.clone();
@@ -143,19 +239,66 @@ impl DnaDefJson {
 
             let zome_content = tokio::fs::read(zome_file_path).await?;
 
-            let wasm: DnaWasm = zome_content.into();
-            let wasm_hash = holo_hash::WasmHash::with_data(&wasm).await;
-            zomes.push((zome_name.clone(), WasmZome { wasm_hash }.into()));
-            wasm_list.push(wasm);
+            zome_wasms.push((zome_name.clone(), zome_content.into()));
         }
 
-        let dna = DnaDef {
-            name: self.name.clone(),
-            uuid: self.uuid.clone(),
-            properties,
-            zomes,
-        };
+        build_dna_file(
+            self.name.clone(),
+            self.uuid.clone(),
+            &self.properties,
+            zome_wasms,
+        )
+        .await
+    }
+}
+
+/// Builds a DNA file from the wasm of its zomes, encoding the JSON properties like `dna-util` does
+pub async fn build_dna_file(
+    name: String,
+    uuid: String,
+    properties: &serde_json::Value,
+    zome_wasms: Vec<(ZomeName, DnaWasm)>,
+) -> Result<DnaFile> {
+    let properties: SerializedBytes = JsonValueDecodeHelper(properties.clone()).try_into()?;
+
+    let mut zomes = Vec::new();
+    let mut wasm_list = Vec::new();
+
+    for (zome_name, wasm) in zome_wasms {
+        let wasm_hash = holo_hash::WasmHash::with_data(&wasm).await;
+        zomes.push((zome_name, WasmZome { wasm_hash }.into()));
+        wasm_list.push(wasm);
+    }
+
+    let dna = DnaDef {
+        name,
+        uuid,
+        properties,
+        zomes,
+    };
 
-        Ok(DnaFile::new(dna, wasm_list).await?)
+    Ok(DnaFile::new(dna, wasm_list).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zome_names_that_leave_the_directory_are_rejected() {
+        for zome_name in &["..", ".", "", "a/b", "a\\b", "/abs"] {
+            assert!(
+                check_zome_name(zome_name).is_err(),
+                "{:?} should be rejected",
+                zome_name
+            );
+        }
+    }
+
+    #[test]
+    fn plain_zome_names_are_accepted() {
+        for zome_name in &["todos", "file_storage", "my-zome.v2"] {
+            assert!(check_zome_name(zome_name).is_ok());
+        }
     }
 }
@@ -0,0 +1,116 @@
+use holochain::conductor::api::error::ExternalApiWireError;
+use holochain_serialized_bytes::prelude::*;
+use std::convert::TryFrom;
+use thiserror::Error;
+
+use crate::conductor_api::types::ConductorResponse;
+
+/// Fragments of the errors the conductor returns when the called zome or function doesn't exist,
+/// like `DnaError::ZomeNotFound`, compared in lowercase
+const MISSING_ZOME_OR_FN_ERRORS: [&str; 4] = [
+    "zome not found",
+    "zomenotfound",
+    "function not found",
+    "fnnotfound",
+];
+
+/// Failures of the communication with the compository, each one with its own exit code
+#[derive(Debug, Error)]
+pub enum CompositoryError {
+    #[error("Connection to the conductor failed: {0}")]
+    Connection(String),
+
+    #[error("The conductor returned a {kind} error: {details}")]
+    ConductorWire { kind: String, details: String },
+
+    #[error("Expected a {expected} response from the conductor, but got {actual}")]
+    UnexpectedResponse {
+        expected: &'static str,
+        actual: &'static str,
+    },
+
+    #[error("Could not decode {what}: {details}")]
+    Decode { what: String, details: String },
+
+    #[error("Could not find app {0} in the conductor")]
+    MissingApp(String),
+
+    #[error("{0}")]
+    MissingCell(String),
+
+    #[error("{0}")]
+    InvalidInput(String),
+}
+
+impl CompositoryError {
+    /// Exit code of the process when it fails with this error.
+    /// 1 is left for any other failure, and 2 is also used for arguments rejected by the parser
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CompositoryError::InvalidInput(_) => 2,
+            CompositoryError::Connection(_) => 3,
+            CompositoryError::ConductorWire { .. } => 4,
+            CompositoryError::UnexpectedResponse { .. } => 5,
+            CompositoryError::Decode { .. } => 6,
+            CompositoryError::MissingApp(_) | CompositoryError::MissingCell(_) => 7,
+        }
+    }
+
+    /// Whether the conductor rejected a zome call because the zome or the function doesn't exist in the cell
+    pub fn is_missing_zome_or_fn(&self) -> bool {
+        match self {
+            CompositoryError::ConductorWire { details, .. } => {
+                let details = details.to_lowercase();
+                MISSING_ZOME_OR_FN_ERRORS
+                    .iter()
+                    .any(|fragment| details.contains(fragment))
+            }
+            _ => false,
+        }
+    }
+
+    pub fn unexpected_response(expected: &'static str, actual: &impl ConductorResponse) -> Self {
+        CompositoryError::UnexpectedResponse {
+            expected,
+            actual: actual.kind(),
+        }
+    }
+}
+
+impl From<ExternalApiWireError> for CompositoryError {
+    fn from(error: ExternalApiWireError) -> Self {
+        let (kind, details) = match error {
+            ExternalApiWireError::RibosomeError(details) => ("RibosomeError", details),
+            ExternalApiWireError::ZomeCallUnauthorized(details) => {
+                ("ZomeCallUnauthorized", details)
+            }
+            ExternalApiWireError::InternalError(details) => ("InternalError", details),
+            other => ("other", format!("{:?}", other)),
+        };
+
+        CompositoryError::ConductorWire {
+            kind: kind.into(),
+            details,
+        }
+    }
+}
+
+/// Decodes the payload of a zome call response into the expected type
+pub fn decode<T>(bytes: SerializedBytes, what: &str) -> Result<T, CompositoryError>
+where
+    T: TryFrom<SerializedBytes, Error = SerializedBytesError>,
+{
+    T::try_from(bytes).map_err(|error| CompositoryError::Decode {
+        what: what.into(),
+        details: format!("{:?}", error),
+    })
+}
+
+/// Exit code for the error, taken from the first `CompositoryError` in its chain of causes
+pub fn exit_code(error: &anyhow::Error) -> i32 {
+    error
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<CompositoryError>())
+        .map(|compository_error| compository_error.exit_code())
+        .unwrap_or(1)
+}
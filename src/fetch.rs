@@ -0,0 +1,88 @@
+use std::path::Path;
+
+use crate::{client::CompositoryClient, dna_file::check_zome_name, types::ZomeToPublish};
+use anyhow::{anyhow, Context, Result};
+use holo_hash::WasmHash;
+use holochain_types::dna::wasm::DnaWasm;
+
+use self::file_download::download_file;
+
+mod file_download;
+
+/// A published zome with its wasm code and UI bundle downloaded from the `file_storage` zome
+#[derive(Debug, Clone)]
+pub struct FetchedZome {
+    pub zome_def: ZomeToPublish,
+    pub wasm_code: Vec<u8>,
+    pub components_bundle: Option<Vec<u8>>,
+}
+
+pub async fn fetch_zome(client: &CompositoryClient, zome_def_hash: String) -> Result<FetchedZome> {
+    let (zome_def, wasm_code) = fetch_zome_wasm(client, zome_def_hash).await?;
+
+    let components_bundle = match zome_def.components_bundle_file.clone() {
+        Some(bundle_file) => Some(download_file(client, bundle_file).await.with_context(|| {
+            format!("Failed to download the UI bundle of zome {}", zome_def.name)
+        })?),
+        None => None,
+    };
+
+    Ok(FetchedZome {
+        zome_def,
+        wasm_code,
+        components_bundle,
+    })
+}
+
+/// Downloads only the wasm of a published zome, checking that it matches the `wasm_hash` of its zome def
+pub async fn fetch_zome_wasm(
+    client: &CompositoryClient,
+    zome_def_hash: String,
+) -> Result<(ZomeToPublish, Vec<u8>)> {
+    let zome_def = client.get_zome_def(&zome_def_hash).await?;
+
+    let wasm_code = download_file(client, zome_def.wasm_file.clone())
+        .await
+        .with_context(|| format!("Failed to download the wasm of zome {}", zome_def.name))?;
+
+    let wasm_hash = WasmHash::with_data(&DnaWasm::from(wasm_code.clone())).await;
+    if wasm_hash != zome_def.wasm_hash {
+        return Err(anyhow!(
+            "The downloaded wasm for zome {} hashes to {}, but the zome def {} records {}",
+            zome_def.name,
+            wasm_hash,
+            zome_def_hash,
+            zome_def.wasm_hash
+        ));
+    }
+
+    Ok((zome_def, wasm_code))
+}
+
+/// Writes `<zome name>.wasm` and, if the zome has UI, `<zome name>.bundle.js` into the given directory
+pub async fn write_zome(fetched_zome: &FetchedZome, output_dir: &impl AsRef<Path>) -> Result<()> {
+    check_zome_name(&fetched_zome.zome_def.name)?;
+
+    let output_dir = output_dir.as_ref();
+    tokio::fs::create_dir_all(output_dir).await?;
+
+    let wasm_path = output_dir.join(format!("{}.wasm", fetched_zome.zome_def.name));
+    tokio::fs::write(&wasm_path, &fetched_zome.wasm_code).await?;
+    println!(
+        "Wrote wasm for zome {} to {}",
+        fetched_zome.zome_def.name,
+        wasm_path.display()
+    );
+
+    if let Some(bundle) = &fetched_zome.components_bundle {
+        let bundle_path = output_dir.join(format!("{}.bundle.js", fetched_zome.zome_def.name));
+        tokio::fs::write(&bundle_path, bundle).await?;
+        println!(
+            "Wrote UI bundle for zome {} to {}",
+            fetched_zome.zome_def.name,
+            bundle_path.display()
+        );
+    }
+
+    Ok(())
+}
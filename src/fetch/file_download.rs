@@ -0,0 +1,26 @@
+use anyhow::{anyhow, Result};
+
+use crate::client::CompositoryClient;
+
+pub async fn download_file(client: &CompositoryClient, file_hash: String) -> Result<Vec<u8>> {
+    let file_metadata = client.get_file_metadata(&file_hash).await?;
+
+    let mut content: Vec<u8> = Vec::with_capacity(file_metadata.size);
+
+    for chunk_hash in file_metadata.chunks_hashes {
+        let chunk = client.get_file_chunk(&chunk_hash).await?;
+
+        content.extend(chunk.0);
+    }
+
+    if content.len() != file_metadata.size {
+        return Err(anyhow!(
+            "File {} should have {} bytes, but its chunks have {} bytes",
+            file_hash,
+            file_metadata.size,
+            content.len()
+        ));
+    }
+
+    Ok(content)
+}
@@ -0,0 +1,64 @@
+use std::path::Path;
+
+use anyhow::Result;
+use holo_hash::HasHash;
+use holochain_zome_types::zome::ZomeName;
+use serde_json::json;
+
+use crate::dna_file::{read_dna, DnaSource};
+
+/// Prints what would be published from the DNA workdir, without connecting to any conductor
+pub async fn inspect_workdir(workdir: &Path, as_json: bool) -> Result<()> {
+    // The dna.json is only read again for the UI paths, the DNA is compiled once by `load`
+    let dna_def_json = read_dna(&workdir).await?;
+    let loaded_dna = DnaSource::Workdir(workdir.to_path_buf()).load().await?;
+    let zomes = loaded_dna.zomes()?;
+
+    let dna_hash = format!("{}", loaded_dna.dna_file.dna.as_hash());
+
+    let zomes_json: Vec<serde_json::Value> = zomes
+        .iter()
+        .map(|zome| {
+            let ui_path = dna_def_json
+                .zomes
+                .get(&ZomeName::from(zome.name.clone()))
+                .and_then(|zome_json| zome_json.ui_path.clone());
+
+            json!({
+                "name": zome.name,
+                "wasm_size": zome.wasm_code.code.len(),
+                "wasm_hash": format!("{}", zome.wasm_hash),
+                "entry_defs": zome.entry_defs,
+                "ui_path": ui_path,
+                "ui_size": zome.components_bundle.as_ref().map(|bundle| bundle.len()),
+            })
+        })
+        .collect();
+
+    if as_json {
+        let report = json!({
+            "name": dna_def_json.name,
+            "dna_hash": dna_hash,
+            "zomes": zomes_json,
+        });
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    println!("DNA {} with hash {}", dna_def_json.name, dna_hash);
+    for (zome, zome_json) in zomes.iter().zip(zomes_json.iter()) {
+        println!();
+        println!("Zome {}", zome.name);
+        println!("  wasm size:  {} bytes", zome.wasm_code.code.len());
+        println!("  wasm hash:  {}", zome.wasm_hash);
+        println!("  entry defs: [{}]", zome.entry_defs.join(", "));
+        match (zome_json["ui_path"].as_str(), &zome.components_bundle) {
+            (Some(ui_path), Some(bundle)) => {
+                println!("  ui bundle:  {} ({} bytes)", ui_path, bundle.len())
+            }
+            _ => println!("  ui bundle:  none"),
+        }
+    }
+
+    Ok(())
+}
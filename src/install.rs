@@ -0,0 +1,75 @@
+use anyhow::{Context, Result};
+use holochain_types::dna::wasm::DnaWasm;
+use holochain_zome_types::{zome::ZomeName, CellId};
+
+use crate::{
+    client::CompositoryClient, conductor_api::admin_websocket::AdminWebsocket,
+    dna_file::build_dna_file, fetch::fetch_zome_wasm,
+};
+
+/// Builds a DNA from the zomes of a published template, with the given uuid and properties,
+/// and installs and activates it as a new app in the conductor
+pub async fn install_dna_template(
+    client: &CompositoryClient,
+    admin_ws: &mut AdminWebsocket,
+    dna_template_hash: String,
+    uuid: String,
+    properties: serde_json::Value,
+    installed_app_id: Option<String>,
+) -> Result<CellId> {
+    let dna_template = client.get_dna_template(&dna_template_hash).await?;
+    println!(
+        "Installing DNA template {} with {} zomes",
+        dna_template.name,
+        dna_template.zome_defs.len()
+    );
+
+    let mut zome_wasms: Vec<(ZomeName, DnaWasm)> = vec![];
+    for zome_reference in dna_template.zome_defs {
+        let (_, wasm_code) = fetch_zome_wasm(client, zome_reference.zome_def_hash.clone())
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to fetch zome {} of the DNA template",
+                    zome_reference.name
+                )
+            })?;
+        println!(
+            "Downloaded zome {} ({} bytes)",
+            zome_reference.name,
+            wasm_code.len()
+        );
+
+        zome_wasms.push((zome_reference.name.into(), wasm_code.into()));
+    }
+
+    let dna_file = build_dna_file(dna_template.name.clone(), uuid, &properties, zome_wasms).await?;
+    let installed_app_id = installed_app_id.unwrap_or_else(|| dna_template.name.clone());
+
+    let agent_key = admin_ws.generate_agent_pub_key().await?;
+    let installed_app = admin_ws
+        .install_app(
+            installed_app_id.clone(),
+            agent_key,
+            dna_template.name.clone(),
+            &dna_file,
+        )
+        .await?;
+    admin_ws.activate_app(installed_app_id.clone()).await?;
+
+    let cell_id = installed_app
+        .cell_data
+        .into_iter()
+        .next()
+        .map(|cell| cell.into_id())
+        .context("The installed app has no cells")?;
+
+    println!(
+        "Installed and activated app {} with dna {} and agent {}",
+        installed_app_id,
+        cell_id.dna_hash(),
+        cell_id.agent_pubkey()
+    );
+
+    Ok(cell_id)
+}
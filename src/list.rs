@@ -0,0 +1,186 @@
+use crate::{
+    client::CompositoryClient,
+    types::{DnaTemplate, ZomeToPublish},
+};
+use anyhow::Result;
+use serde_json::json;
+
+/// Published zome def, with its hash already encoded to a string
+#[derive(Debug, Clone)]
+pub struct ZomeDefWithHash {
+    pub hash: String,
+    pub zome_def: ZomeToPublish,
+}
+
+/// Published DNA template, with its hash already encoded to a string
+#[derive(Debug, Clone)]
+pub struct DnaTemplateWithHash {
+    pub hash: String,
+    pub dna_template: DnaTemplate,
+}
+
+pub async fn get_all_zome_defs(client: &CompositoryClient) -> Result<Vec<ZomeDefWithHash>> {
+    let output = client.get_all_zome_defs().await?;
+
+    Ok(output
+        .0
+        .into_iter()
+        .map(|(hash, zome_def)| ZomeDefWithHash {
+            hash: format!("{}", hash.0),
+            zome_def,
+        })
+        .collect())
+}
+
+pub async fn get_all_dna_templates(client: &CompositoryClient) -> Result<Vec<DnaTemplateWithHash>> {
+    let output = client.get_all_dna_templates().await?;
+
+    Ok(output
+        .0
+        .into_iter()
+        .map(|(hash, dna_template)| DnaTemplateWithHash {
+            hash: format!("{}", hash.0),
+            dna_template,
+        })
+        .collect())
+}
+
+pub fn print_zome_defs(zome_defs: &[ZomeDefWithHash], as_json: bool) -> Result<()> {
+    if as_json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&zome_defs_to_json(zome_defs))?
+        );
+        return Ok(());
+    }
+
+    let rows: Vec<Vec<String>> = zome_defs
+        .iter()
+        .map(|z| {
+            vec![
+                z.zome_def.name.clone(),
+                z.hash.clone(),
+                z.zome_def.entry_defs.join(","),
+                yes_no(z.zome_def.components_bundle_file.is_some()),
+            ]
+        })
+        .collect();
+
+    print_table(&["NAME", "HASH", "ENTRY DEFS", "UI"], rows);
+    Ok(())
+}
+
+pub fn print_dna_templates(dna_templates: &[DnaTemplateWithHash], as_json: bool) -> Result<()> {
+    if as_json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&dna_templates_to_json(dna_templates))?
+        );
+        return Ok(());
+    }
+
+    let rows: Vec<Vec<String>> = dna_templates
+        .iter()
+        .map(|t| {
+            let zome_names: Vec<String> = t
+                .dna_template
+                .zome_defs
+                .iter()
+                .map(|z| z.name.clone())
+                .collect();
+
+            vec![
+                t.dna_template.name.clone(),
+                t.hash.clone(),
+                zome_names.join(","),
+            ]
+        })
+        .collect();
+
+    print_table(&["NAME", "HASH", "ZOMES"], rows);
+    Ok(())
+}
+
+pub fn print_search_results(
+    zome_defs: &[ZomeDefWithHash],
+    dna_templates: &[DnaTemplateWithHash],
+    as_json: bool,
+) -> Result<()> {
+    if as_json {
+        let results = json!({
+            "zomes": zome_defs_to_json(zome_defs),
+            "templates": dna_templates_to_json(dna_templates),
+        });
+        println!("{}", serde_json::to_string_pretty(&results)?);
+        return Ok(());
+    }
+
+    print_zome_defs(zome_defs, false)?;
+    println!();
+    print_dna_templates(dna_templates, false)
+}
+
+/** Helper functions */
+
+fn zome_defs_to_json(zome_defs: &[ZomeDefWithHash]) -> serde_json::Value {
+    zome_defs
+        .iter()
+        .map(|z| {
+            json!({
+                "name": z.zome_def.name,
+                "hash": z.hash,
+                "wasm_hash": format!("{}", z.zome_def.wasm_hash),
+                "entry_defs": z.zome_def.entry_defs,
+                "has_ui": z.zome_def.components_bundle_file.is_some(),
+            })
+        })
+        .collect()
+}
+
+fn dna_templates_to_json(dna_templates: &[DnaTemplateWithHash]) -> serde_json::Value {
+    dna_templates
+        .iter()
+        .map(|t| {
+            json!({
+                "name": t.dna_template.name,
+                "hash": t.hash,
+                "zome_defs": t.dna_template.zome_defs,
+            })
+        })
+        .collect()
+}
+
+fn yes_no(value: bool) -> String {
+    match value {
+        true => "yes".into(),
+        false => "no".into(),
+    }
+}
+
+fn print_table(headers: &[&str], rows: Vec<Vec<String>>) {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.len()).collect();
+    for row in rows.iter() {
+        for (index, cell) in row.iter().enumerate() {
+            widths[index] = widths[index].max(cell.len());
+        }
+    }
+
+    let format_row = |cells: Vec<String>| {
+        cells
+            .iter()
+            .enumerate()
+            .map(|(index, cell)| format!("{:width$}", cell, width = widths[index]))
+            .collect::<Vec<String>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+
+    println!(
+        "{}",
+        format_row(headers.iter().map(|h| h.to_string()).collect())
+    );
+    for row in rows {
+        println!("{}", format_row(row));
+    }
+}
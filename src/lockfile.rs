@@ -0,0 +1,225 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use anyhow::{Context, Result};
+use holo_hash::DnaHash;
+use serde::{Deserialize, Serialize};
+
+use crate::{dna_file::DnaSource, types::ZomeToPublish};
+
+const LOCKFILE_NAME: &str = "compository.lock";
+
+/// Records what was published from a DNA workdir, and into which compository
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Lockfile {
+    pub compository_dna_hash: String,
+    pub zomes: BTreeMap<String, LockedZome>, // Indexed by zome name
+    pub dna_template_hash: Option<String>,
+    pub instantiated_dna_hash: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockedZome {
+    pub wasm_hash: String,
+    pub wasm_file: String,
+    pub components_bundle_file: Option<String>,
+    /// Hash of the contents of the UI bundle, to publish the zome again if only its UI changed
+    #[serde(default)]
+    pub components_bundle_hash: Option<String>,
+    pub zome_def_hash: String,
+}
+
+impl LockedZome {
+    pub fn new(
+        zome_def_hash: String,
+        zome_to_publish: &ZomeToPublish,
+        components_bundle: Option<&[u8]>,
+    ) -> Self {
+        LockedZome {
+            wasm_hash: format!("{}", zome_to_publish.wasm_hash),
+            wasm_file: zome_to_publish.wasm_file.clone(),
+            components_bundle_file: zome_to_publish.components_bundle_file.clone(),
+            components_bundle_hash: components_bundle.map(bundle_hash),
+            zome_def_hash,
+        }
+    }
+}
+
+impl Lockfile {
+    /// Reads the lockfile of the DNA, discarding it if it was written for another compository
+    pub async fn load(dna_source: &DnaSource, compository_dna_hash: &DnaHash) -> Result<Lockfile> {
+        let compository_dna_hash = format!("{}", compository_dna_hash);
+        let empty_lockfile = Lockfile {
+            compository_dna_hash: compository_dna_hash.clone(),
+            zomes: BTreeMap::new(),
+            dna_template_hash: None,
+            instantiated_dna_hash: None,
+        };
+
+        let lockfile_path = lockfile_path(dna_source);
+        if !lockfile_path.exists() {
+            return Ok(empty_lockfile);
+        }
+
+        let lockfile_data = tokio::fs::read(&lockfile_path).await?;
+        let lockfile: Lockfile = serde_json::from_slice(&lockfile_data)
+            .with_context(|| format!("Invalid lockfile {}", lockfile_path.display()))?;
+
+        if lockfile.compository_dna_hash != compository_dna_hash {
+            println!(
+                "Ignoring {}, it was written for the compository with dna {}",
+                lockfile_path.display(),
+                lockfile.compository_dna_hash
+            );
+            return Ok(empty_lockfile);
+        }
+
+        Ok(lockfile)
+    }
+
+    pub async fn write(&self, dna_source: &DnaSource) -> Result<()> {
+        let lockfile_path = lockfile_path(dna_source);
+
+        let lockfile_data = serde_json::to_vec_pretty(self)?;
+        tokio::fs::write(&lockfile_path, lockfile_data)
+            .await
+            .with_context(|| format!("Failed to write {}", lockfile_path.display()))?;
+
+        println!("Wrote {}", lockfile_path.display());
+
+        Ok(())
+    }
+
+    /// Forgets the zomes that are not in the DNA anymore, and the template and instance they were part of
+    pub fn retain_zomes(&mut self, zome_names: &[String]) {
+        let locked_zomes_count = self.zomes.len();
+        self.zomes
+            .retain(|zome_name, _| zome_names.contains(zome_name));
+
+        if self.zomes.len() != locked_zomes_count {
+            self.forget_dna_hashes();
+        }
+    }
+
+    /// Records a newly published version of the zome, forgetting the template and instance
+    /// that were published with the previous one
+    pub fn update_zome(&mut self, zome_name: String, locked_zome: LockedZome) {
+        self.zomes.insert(zome_name, locked_zome);
+        self.forget_dna_hashes();
+    }
+
+    /// Records the published template, forgetting the instance of the previous one
+    pub fn update_dna_template_hash(&mut self, dna_template_hash: String) {
+        if self.dna_template_hash.as_ref() != Some(&dna_template_hash) {
+            self.instantiated_dna_hash = None;
+        }
+        self.dna_template_hash = Some(dna_template_hash);
+    }
+
+    /// Returns the locked zome if it was published with the same wasm and UI bundle
+    pub fn get_unchanged_zome(
+        &self,
+        zome_name: &str,
+        wasm_hash: &str,
+        components_bundle: Option<&[u8]>,
+    ) -> Option<&LockedZome> {
+        let components_bundle_hash = components_bundle.map(bundle_hash);
+
+        self.zomes.get(zome_name).filter(|locked_zome| {
+            locked_zome.wasm_hash == wasm_hash
+                && locked_zome.components_bundle_hash == components_bundle_hash
+        })
+    }
+
+    fn forget_dna_hashes(&mut self) {
+        self.dna_template_hash = None;
+        self.instantiated_dna_hash = None;
+    }
+}
+
+/// Hash of the contents of a UI bundle, as the uploaded file is only known by its entry hash
+fn bundle_hash(components_bundle: &[u8]) -> String {
+    blake2b_simd::blake2b(components_bundle)
+        .to_hex()
+        .to_string()
+}
+
+/// `compository.lock` inside the workdir, or `<name>.dna.lock` next to a packed `<name>.dna.gz`
+fn lockfile_path(dna_source: &DnaSource) -> PathBuf {
+    match dna_source {
+        DnaSource::Workdir(dna_work_dir) => dna_work_dir.join(LOCKFILE_NAME),
+        DnaSource::Packed(dna_file_path) => dna_file_path.with_extension("lock"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn locked_zome(zome_def_hash: &str) -> LockedZome {
+        LockedZome {
+            wasm_hash: "uhC0k".into(),
+            wasm_file: "uhCEk".into(),
+            components_bundle_file: None,
+            components_bundle_hash: None,
+            zome_def_hash: zome_def_hash.into(),
+        }
+    }
+
+    fn published_lockfile() -> Lockfile {
+        let mut zomes = BTreeMap::new();
+        zomes.insert("todos".to_string(), locked_zome("todos-def"));
+        zomes.insert("profiles".to_string(), locked_zome("profiles-def"));
+
+        Lockfile {
+            compository_dna_hash: "uhC0k".into(),
+            zomes,
+            dna_template_hash: Some("template".into()),
+            instantiated_dna_hash: Some("instance".into()),
+        }
+    }
+
+    #[test]
+    fn removed_zomes_are_forgotten_with_the_dna_hashes() {
+        let mut lockfile = published_lockfile();
+
+        lockfile.retain_zomes(&["todos".to_string()]);
+
+        assert_eq!(lockfile.zomes.keys().collect::<Vec<_>>(), vec!["todos"]);
+        assert_eq!(lockfile.dna_template_hash, None);
+        assert_eq!(lockfile.instantiated_dna_hash, None);
+    }
+
+    #[test]
+    fn unchanged_zomes_keep_the_dna_hashes() {
+        let mut lockfile = published_lockfile();
+
+        lockfile.retain_zomes(&["todos".to_string(), "profiles".to_string()]);
+
+        assert_eq!(lockfile.zomes.len(), 2);
+        assert_eq!(lockfile.dna_template_hash, Some("template".into()));
+        assert_eq!(lockfile.instantiated_dna_hash, Some("instance".into()));
+    }
+
+    #[test]
+    fn updated_zomes_forget_the_dna_hashes() {
+        let mut lockfile = published_lockfile();
+
+        lockfile.update_zome("todos".into(), locked_zome("new-todos-def"));
+
+        assert_eq!(lockfile.zomes["todos"].zome_def_hash, "new-todos-def");
+        assert_eq!(lockfile.dna_template_hash, None);
+        assert_eq!(lockfile.instantiated_dna_hash, None);
+    }
+
+    #[test]
+    fn another_template_forgets_the_instance() {
+        let mut lockfile = published_lockfile();
+
+        lockfile.update_dna_template_hash("template".into());
+        assert_eq!(lockfile.instantiated_dna_hash, Some("instance".into()));
+
+        lockfile.update_dna_template_hash("other-template".into());
+        assert_eq!(lockfile.dna_template_hash, Some("other-template".into()));
+        assert_eq!(lockfile.instantiated_dna_hash, None);
+    }
+}
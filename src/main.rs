@@ -1,88 +1,623 @@
-use conductor_api::{app_websocket::AppWebsocket, types::ClientAppResponse};
-use holochain_types::app::InstalledCell;
-use holochain_zome_types::CellId;
-use publish::publish_zomes;
-use structopt::StructOpt;
+use cell::{get_compository_cell_id, parse_dna_hash};
+use client::{check_entry_hash, CompositoryClient};
+use compose::compose_dna;
+use conductor_api::{
+    admin_websocket::AdminWebsocket,
+    app_websocket::{AppWebsocket, RequestConfig},
+};
+use fetch::{fetch_zome, write_zome};
+use holo_hash::DnaHash;
+use inspect::inspect_workdir;
+use install::install_dna_template;
+use list::{
+    get_all_dna_templates, get_all_zome_defs, print_dna_templates, print_search_results,
+    print_zome_defs,
+};
+use lockfile::Lockfile;
+use pack::{pack_workdir, unpack_dna_file};
+use progress::{Progress, ProgressMode};
+use publish::{
+    default_state_dir, dry_run_publish_dna_template, dry_run_publish_instantiated_dna,
+    dry_run_publish_zomes, parse_chunk_size, parse_jobs, publish_dna_template,
+    publish_instantiated_dna, publish_zomes, UploadConfig, DEFAULT_CHUNK_SIZE,
+};
+use setup::setup_compository;
+use structopt::{clap::ErrorKind, StructOpt};
+use watch::watch_publications;
 
+mod cell;
+mod client;
+mod compose;
 mod conductor_api;
 mod dna_file;
+mod error;
+mod fetch;
+mod inspect;
+mod install;
+mod list;
+mod lockfile;
+mod pack;
+mod progress;
 mod publish;
+mod setup;
 mod types;
+mod watch;
 
-use anyhow::{anyhow, Result};
-use dna_file::{get_zomes, read_dna};
+use anyhow::Result;
+use dna_file::DnaSource;
+use error::CompositoryError;
 use tracing::instrument;
 
 #[derive(Debug, StructOpt)]
-#[structopt(name = "compository-publish")]
-struct Opt {
+#[structopt(name = "compository")]
+enum Opt {
+    /// Publishes every zome of the DNA workdir into the compository
+    PublishZomes {
+        #[structopt(flatten)]
+        dna: DnaSourceOpt,
+        #[structopt(flatten)]
+        conductor: ConductorOpt,
+        #[structopt(flatten)]
+        publish: PublishOpt,
+    },
+    /// Publishes every zome of the DNA workdir and a DNA template referencing them
+    PublishTemplate {
+        #[structopt(flatten)]
+        dna: DnaSourceOpt,
+        #[structopt(flatten)]
+        conductor: ConductorOpt,
+        #[structopt(flatten)]
+        publish: PublishOpt,
+    },
+    /// Registers the DNA compiled from the workdir, with its uuid and properties,
+    /// as an instance of a DNA template
+    PublishInstance {
+        #[structopt(flatten)]
+        dna: DnaSourceOpt,
+        #[structopt(flatten)]
+        conductor: ConductorOpt,
+        #[structopt(flatten)]
+        publish: PublishOpt,
+        /// Already published DNA template; if not given, the template is published from the workdir
+        #[structopt(short = "t", long = "template-hash", parse(try_from_str = check_entry_hash))]
+        template_hash: Option<String>,
+    },
+    /// Downloads a published zome, writing its wasm and UI bundle to disk
+    Fetch {
+        #[structopt(flatten)]
+        conductor: ConductorOpt,
+        /// Hash of the published zome def
+        #[structopt(parse(try_from_str = check_entry_hash))]
+        zome_def_hash: String,
+        /// Directory where the downloaded files are written
+        #[structopt(short = "o", long = "output", default_value = ".")]
+        output: std::path::PathBuf,
+    },
+    /// Downloads published zomes into a new DNA workdir, with a dna.json that references them
+    Compose {
+        #[structopt(flatten)]
+        conductor: ConductorOpt,
+        /// Name of the new DNA
+        dna_name: String,
+        /// Hashes of the published zome defs to include in the DNA
+        #[structopt(required = true, parse(try_from_str = check_entry_hash))]
+        zome_def_hashes: Vec<String>,
+        /// Directory where the `<dna name>.dna.workdir` is created
+        #[structopt(short = "o", long = "output", default_value = ".")]
+        output: std::path::PathBuf,
+    },
+    /// Prints what would be published from the DNA workdir, without connecting to a conductor
+    Inspect {
+        #[structopt(flatten)]
+        dna: DnaOpt,
+        #[structopt(flatten)]
+        output: OutputOpt,
+    },
+    /// Compiles the DNA workdir into a packed DNA file (.dna.gz)
+    Pack {
+        #[structopt(flatten)]
+        dna: DnaOpt,
+        /// Path of the DNA file, `<dna name>.dna.gz` next to the workdir by default
+        #[structopt(short = "o", long = "output")]
+        output: Option<std::path::PathBuf>,
+    },
+    /// Writes the zomes of a packed DNA file (.dna.gz) into a new DNA workdir, with a dna.json that references them
+    Unpack {
+        /// Path of the packed DNA file
+        dna_file: std::path::PathBuf,
+        /// Directory of the new workdir, `<dna name>.dna.workdir` next to the DNA file by default
+        #[structopt(short = "o", long = "output")]
+        output: Option<std::path::PathBuf>,
+    },
+    /// Prints the zomes and DNA templates published in the compository as they are published
+    Watch {
+        #[structopt(flatten)]
+        conductor: ConductorOpt,
+        #[structopt(flatten)]
+        output: OutputOpt,
+    },
+    /// Lists the zomes or the DNA templates published in the compository
+    List(ListCommand),
+    /// Searches the published zomes and DNA templates whose name contains the given text
+    Search {
+        #[structopt(flatten)]
+        conductor: ConductorOpt,
+        name: String,
+        #[structopt(flatten)]
+        output: OutputOpt,
+    },
+    /// Builds a DNA from the zomes of a published DNA template,
+    /// and installs and activates it in the conductor
+    Install {
+        #[structopt(flatten)]
+        conductor: ConductorOpt,
+        #[structopt(flatten)]
+        admin: AdminOpt,
+        /// Hash of the published DNA template
+        #[structopt(parse(try_from_str = check_entry_hash))]
+        template_hash: String,
+        /// UUID of the new DNA, to get a different DNA hash from other instances of the template
+        #[structopt(long = "uuid", default_value = "")]
+        uuid: String,
+        /// Properties of the new DNA, as a JSON value
+        #[structopt(long = "properties", default_value = "null", parse(try_from_str = serde_json::from_str))]
+        properties: serde_json::Value,
+        /// Id of the new installed app, the name of the template by default
+        #[structopt(long = "app-id")]
+        app_id: Option<String>,
+    },
+    /// Installs and activates the compository DNA in a local conductor,
+    /// and attaches an App interface to publish into it
+    Setup {
+        #[structopt(flatten)]
+        admin: AdminOpt,
+        /// Path to the compository DNA file (.dna.gz)
+        #[structopt(short = "d", long = "dna")]
+        dna_path: std::path::PathBuf,
+        #[structopt(short = "i", long = "installed-app-id", default_value = "compository")]
+        installed_app_id: String,
+        /// Port of the App interface to attach
+        #[structopt(short = "p", long = "app-port", default_value = "22223")]
+        app_port: u16,
+        /// Seconds to wait for the response of each request to the conductor
+        #[structopt(long = "timeout", default_value = "60")]
+        timeout: u64,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+enum ListCommand {
+    /// Lists all the published zomes
+    Zomes {
+        #[structopt(flatten)]
+        conductor: ConductorOpt,
+        #[structopt(flatten)]
+        output: OutputOpt,
+    },
+    /// Lists all the published DNA templates
+    Templates {
+        #[structopt(flatten)]
+        conductor: ConductorOpt,
+        #[structopt(flatten)]
+        output: OutputOpt,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+struct DnaOpt {
     #[structopt(short = "w", long = "workdir")]
     workdir: std::path::PathBuf,
-    #[structopt(short = "-c", long = "compository-dna-hash")]
-    compository_dna_hash: String,
+}
+
+#[derive(Debug, StructOpt)]
+struct DnaSourceOpt {
+    /// DNA workdir, with the dna.json describing its zomes
+    #[structopt(
+        short = "w",
+        long = "workdir",
+        required_unless = "dna-file",
+        conflicts_with = "dna-file"
+    )]
+    workdir: Option<std::path::PathBuf>,
+    /// Packed DNA file (.dna.gz) to publish instead of a workdir. Its zomes are published without UI
+    #[structopt(long = "dna-file")]
+    dna_file: Option<std::path::PathBuf>,
+}
+
+impl DnaSourceOpt {
+    fn dna_source(&self) -> DnaSource {
+        match (&self.workdir, &self.dna_file) {
+            (Some(workdir), _) => DnaSource::Workdir(workdir.clone()),
+            (None, Some(dna_file)) => DnaSource::Packed(dna_file.clone()),
+            (None, None) => unreachable!("structopt requires --workdir or --dna-file"),
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+struct ConductorOpt {
+    /// Hash of the compository DNA, in its base64 form (uhC0k...).
+    /// If not given, the compository cell is looked up among the cells of the installed app
+    #[structopt(short = "c", long = "compository-dna-hash", parse(try_from_str = parse_dna_hash))]
+    compository_dna_hash: Option<DnaHash>,
+    /// URL of the App interface of the conductor, like ws://localhost:22223. Not needed with --dry-run
     #[structopt(short = "u", long = "url")]
-    url: String,
+    url: Option<String>,
+    /// Id of the installed app that contains the compository cell. Not needed with --dry-run
     #[structopt(short = "i", long = "installed-app-id")]
-    installed_app_id: String,
+    installed_app_id: Option<String>,
+    /// Seconds to wait for the response of each request to the conductor
+    #[structopt(long = "timeout", default_value = "60")]
+    timeout: u64,
+    /// Times that a request that can be safely repeated is retried, with exponential backoff
+    #[structopt(long = "retries", default_value = "3")]
+    retries: usize,
+}
+
+impl ConductorOpt {
+    fn request_config(&self) -> RequestConfig {
+        RequestConfig {
+            timeout: std::time::Duration::from_secs(self.timeout),
+            retries: self.retries,
+            ..RequestConfig::default()
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+struct AdminOpt {
+    /// URL of the Admin interface of the conductor
+    #[structopt(
+        short = "a",
+        long = "admin-url",
+        default_value = "ws://localhost:22222"
+    )]
+    admin_url: String,
+}
+
+#[derive(Debug, StructOpt)]
+struct PublishOpt {
+    /// Print the zome calls that would be made, without connecting to the conductor
+    #[structopt(long = "dry-run")]
+    dry_run: bool,
+    /// Maximum number of file chunks uploaded at the same time
+    #[structopt(
+        short = "j",
+        long = "jobs",
+        default_value = "4",
+        parse(try_from_str = parse_jobs)
+    )]
+    jobs: usize,
+    /// Directory where the progress of the uploads is saved, to resume them after a failure
+    #[structopt(long = "state-dir")]
+    state_dir: Option<std::path::PathBuf>,
+    /// Size in bytes of the chunks the files are split into, 4 MiB by default.
+    /// Twice its size must fit in the maximum message size of the websocket,
+    /// as every byte of the chunk can take up to 2 bytes in the message
+    #[structopt(
+        long = "chunk-size",
+        env = "COMPOSITORY_CHUNK_SIZE",
+        parse(try_from_str = parse_chunk_size)
+    )]
+    chunk_size: Option<usize>,
+    /// Don't report the progress of the uploads
+    #[structopt(short = "q", long = "quiet", conflicts_with = "ndjson")]
+    quiet: bool,
+    /// Report the progress of the uploads as one JSON event per line on stderr
+    #[structopt(long = "ndjson")]
+    ndjson: bool,
+}
+
+impl PublishOpt {
+    fn upload_config(&self) -> UploadConfig {
+        UploadConfig {
+            jobs: self.jobs,
+            chunk_size: self.chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE),
+            state_dir: self.state_dir.clone().unwrap_or_else(default_state_dir),
+            progress: Progress::new(self.progress_mode()),
+        }
+    }
+
+    fn progress_mode(&self) -> ProgressMode {
+        match (self.quiet, self.ndjson) {
+            (true, _) => ProgressMode::Quiet,
+            (false, true) => ProgressMode::Ndjson,
+            (false, false) => ProgressMode::Human,
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+struct OutputOpt {
+    /// Print the results as JSON instead of as a table
+    #[structopt(long = "json")]
+    json: bool,
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() {
     tracing_subscriber::fmt::init();
-    run().await
+
+    let opt = match Opt::from_args_safe() {
+        Ok(opt) => opt,
+        Err(error) => match error.kind {
+            ErrorKind::HelpDisplayed | ErrorKind::VersionDisplayed => error.exit(),
+            _ => {
+                eprintln!("{}", error.message);
+                std::process::exit(2);
+            }
+        },
+    };
+
+    if let Err(error) = run(opt).await {
+        eprintln!("Error: {:?}", error);
+        std::process::exit(error::exit_code(&error));
+    }
 }
 
 #[instrument(err)]
-async fn run() -> Result<()> {
-    let opt = Opt::from_args();
+async fn run(opt: Opt) -> Result<()> {
+    match opt {
+        Opt::PublishZomes {
+            dna,
+            conductor,
+            publish,
+        } => {
+            let dna_source = dna.dna_source();
+            let zomes = dna_source.load().await?.zomes()?;
 
-    let dna_def_json = read_dna(&opt.workdir).await?;
+            if publish.dry_run {
+                dry_run_publish_zomes(zomes, &publish.upload_config())?;
+                return Ok(());
+            }
 
-    let _dna_name = dna_def_json.name.clone();
+            let client = connect_to_compository(conductor).await?;
+            let mut lockfile = Lockfile::load(&dna_source, client.cell_id().dna_hash()).await?;
 
-    let zomes = get_zomes(&dna_def_json, &opt.workdir).await?;
+            let result =
+                publish_zomes(&client, zomes, &mut lockfile, &publish.upload_config()).await;
 
-    let mut ws = AppWebsocket::connect(opt.url.clone()).await?;
+            lockfile.write(&dna_source).await?;
+            result?;
+        }
+        Opt::PublishTemplate {
+            dna,
+            conductor,
+            publish,
+        } => {
+            let dna_source = dna.dna_source();
+            let loaded_dna = dna_source.load().await?;
+            let zomes = loaded_dna.zomes()?;
 
-    println!("Connected to the holochain conductor at {}", opt.url);
+            if publish.dry_run {
+                dry_run_publish_dna_template(loaded_dna.name, zomes, &publish.upload_config())?;
+                return Ok(());
+            }
 
-    let compository_cell_id =
-        get_compository_cell_id(&mut ws, opt.installed_app_id, opt.compository_dna_hash).await?;
+            let client = connect_to_compository(conductor).await?;
+            let mut lockfile = Lockfile::load(&dna_source, client.cell_id().dna_hash()).await?;
+
+            let result = publish_dna_template(
+                &client,
+                loaded_dna.name,
+                zomes,
+                &mut lockfile,
+                &publish.upload_config(),
+            )
+            .await;
 
-    println!("Connected to compository with {:?}", compository_cell_id);
+            lockfile.write(&dna_source).await?;
+            result?;
+        }
+        Opt::PublishInstance {
+            dna,
+            conductor,
+            publish,
+            template_hash,
+        } => {
+            let dna_source = dna.dna_source();
+            let loaded_dna = dna_source.load().await?;
+            let dna_file = loaded_dna.dna_file.clone();
 
-    publish_zomes(&mut ws, &compository_cell_id, zomes).await?;
+            let zomes = match template_hash {
+                Some(_) => vec![],
+                None => loaded_dna.zomes()?,
+            };
 
-    Ok(())
-}
+            if publish.dry_run {
+                let template_hash = match template_hash {
+                    Some(template_hash) => template_hash,
+                    None => dry_run_publish_dna_template(
+                        loaded_dna.name,
+                        zomes,
+                        &publish.upload_config(),
+                    )?,
+                };
+                dry_run_publish_instantiated_dna(dna_file, template_hash)?;
+                return Ok(());
+            }
+
+            let client = connect_to_compository(conductor).await?;
+            let mut lockfile = Lockfile::load(&dna_source, client.cell_id().dna_hash()).await?;
+
+            let result = async {
+                let template_hash = match template_hash {
+                    Some(template_hash) => template_hash,
+                    None => {
+                        publish_dna_template(
+                            &client,
+                            loaded_dna.name,
+                            zomes,
+                            &mut lockfile,
+                            &publish.upload_config(),
+                        )
+                        .await?
+                    }
+                };
+
+                publish_instantiated_dna(&client, dna_file, template_hash, &mut lockfile).await
+            }
+            .await;
+
+            lockfile.write(&dna_source).await?;
+            result?;
+        }
+        Opt::Fetch {
+            conductor,
+            zome_def_hash,
+            output,
+        } => {
+            let client = connect_to_compository(conductor).await?;
+
+            let fetched_zome = fetch_zome(&client, zome_def_hash).await?;
+
+            write_zome(&fetched_zome, &output).await?;
+        }
+        Opt::Compose {
+            conductor,
+            dna_name,
+            zome_def_hashes,
+            output,
+        } => {
+            let client = connect_to_compository(conductor).await?;
+
+            compose_dna(&client, dna_name, zome_def_hashes, &output).await?;
+        }
+        Opt::Inspect { dna, output } => {
+            inspect_workdir(&dna.workdir, output.json).await?;
+        }
+        Opt::Pack { dna, output } => {
+            pack_workdir(&dna.workdir, output).await?;
+        }
+        Opt::Unpack { dna_file, output } => {
+            unpack_dna_file(&dna_file, output).await?;
+        }
+        Opt::Watch { conductor, output } => {
+            let client = connect_to_compository(conductor).await?;
+
+            watch_publications(&client, output.json).await?;
+        }
+        Opt::List(ListCommand::Zomes { conductor, output }) => {
+            let client = connect_to_compository(conductor).await?;
+
+            let zome_defs = get_all_zome_defs(&client).await?;
+
+            print_zome_defs(&zome_defs, output.json)?;
+        }
+        Opt::List(ListCommand::Templates { conductor, output }) => {
+            let client = connect_to_compository(conductor).await?;
 
-async fn get_compository_cell_id(
-    ws: &mut AppWebsocket,
-    installed_app_id: String,
-    compository_dna_hash: String,
-) -> Result<CellId> {
-    let app_info = ws.app_info(installed_app_id.clone()).await?;
+            let dna_templates = get_all_dna_templates(&client).await?;
 
-    match app_info {
-        ClientAppResponse::AppInfo(Some(info)) => {
-            find_cell_for_dna(compository_dna_hash, info.cell_data).map(|c| c.into_id())
+            print_dna_templates(&dna_templates, output.json)?;
+        }
+        Opt::Search {
+            conductor,
+            name,
+            output,
+        } => {
+            let client = connect_to_compository(conductor).await?;
+
+            let name = name.to_lowercase();
+            let matches = |candidate: &String| candidate.to_lowercase().contains(&name);
+
+            let zome_defs: Vec<_> = get_all_zome_defs(&client)
+                .await?
+                .into_iter()
+                .filter(|z| matches(&z.zome_def.name))
+                .collect();
+            let dna_templates: Vec<_> = get_all_dna_templates(&client)
+                .await?
+                .into_iter()
+                .filter(|t| matches(&t.dna_template.name))
+                .collect();
+
+            print_search_results(&zome_defs, &dna_templates, output.json)?;
+        }
+        Opt::Install {
+            conductor,
+            admin,
+            template_hash,
+            uuid,
+            properties,
+            app_id,
+        } => {
+            let request_config = conductor.request_config();
+            let client = connect_to_compository(conductor).await?;
+            let mut admin_ws = connect_to_admin(&admin, request_config).await?;
+
+            install_dna_template(
+                &client,
+                &mut admin_ws,
+                template_hash,
+                uuid,
+                properties,
+                app_id,
+            )
+            .await?;
+        }
+        Opt::Setup {
+            admin,
+            dna_path,
+            installed_app_id,
+            app_port,
+            timeout,
+        } => {
+            let request_config = RequestConfig {
+                timeout: std::time::Duration::from_secs(timeout),
+                ..RequestConfig::default()
+            };
+            let mut admin_ws = connect_to_admin(&admin, request_config).await?;
+
+            setup_compository(&mut admin_ws, &dna_path, installed_app_id, app_port).await?;
         }
-        ClientAppResponse::AppInfo(None) => Err(anyhow!(format!(
-            "Could not find app with it {}",
-            installed_app_id
-        ))),
-        _ => Err(anyhow!("Bad response")),
     }
+
+    Ok(())
 }
 
-fn find_cell_for_dna(dna_hash: String, cells: Vec<InstalledCell>) -> Result<InstalledCell> {
-    let maybe_compository_cell = cells.into_iter().find(|cell| {
-        let dna_hash = format!("{:?}", cell.clone().into_id().dna_hash());
-        dna_hash == dna_hash
-    });
+/// Reports the connection on stderr, so that the `--json` output of the commands stays parseable
+async fn connect_to_compository(conductor: ConductorOpt) -> Result<CompositoryClient> {
+    let url = required_arg(conductor.url.clone(), "--url")?;
+    let installed_app_id = required_arg(conductor.installed_app_id.clone(), "--installed-app-id")?;
+
+    let mut ws = AppWebsocket::connect(url.clone(), conductor.request_config()).await?;
+
+    eprintln!("Connected to the holochain conductor at {}", url);
+
+    let compository_cell_id =
+        get_compository_cell_id(&mut ws, installed_app_id, conductor.compository_dna_hash).await?;
+
+    eprintln!(
+        "Connected to compository with dna {} and agent {}",
+        compository_cell_id.dna_hash(),
+        compository_cell_id.agent_pubkey()
+    );
+
+    Ok(CompositoryClient::new(ws, compository_cell_id))
+}
+
+/// Arguments that are only optional so that `--dry-run` can be used without a conductor
+fn required_arg<T>(value: Option<T>, name: &str) -> Result<T> {
+    value.ok_or_else(|| {
+        CompositoryError::InvalidInput(format!(
+            "The argument {} is required to connect to the conductor",
+            name
+        ))
+        .into()
+    })
+}
+
+async fn connect_to_admin(
+    admin: &AdminOpt,
+    request_config: RequestConfig,
+) -> Result<AdminWebsocket> {
+    let admin_ws = AdminWebsocket::connect(admin.admin_url.clone(), request_config).await?;
+
+    println!(
+        "Connected to the admin interface of the conductor at {}",
+        admin.admin_url
+    );
 
-    maybe_compository_cell.ok_or(anyhow!(format!(
-        "Could not find dna {} in this installed app",
-        dna_hash
-    )))
+    Ok(admin_ws)
 }
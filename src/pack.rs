@@ -0,0 +1,126 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context, Result};
+use holo_hash::HasHash;
+use holochain_zome_types::zome::ZomeName;
+
+use crate::dna_file::{
+    check_zome_name, decode_properties, read_dna, read_dna_file, DnaDefJson, ZomeJson,
+};
+
+/// Compiles the workdir into a packed DNA file, written to `<dna name>.dna.gz` next to the workdir
+/// if no output path is given
+pub async fn pack_workdir(workdir: &Path, output: Option<PathBuf>) -> Result<PathBuf> {
+    let workdir = workdir.canonicalize()?;
+    let dna_def_json = read_dna(&workdir).await?;
+    let dna_file = dna_def_json.compile_dna_file(workdir.clone()).await?;
+
+    let output = match output {
+        Some(output) => output,
+        None => workdir
+            .parent()
+            .unwrap_or(&workdir)
+            .join(format!("{}.dna.gz", dna_def_json.name)),
+    };
+
+    tokio::fs::write(&output, dna_file.to_file_content().await?)
+        .await
+        .with_context(|| format!("Failed to write {}", output.display()))?;
+
+    println!(
+        "Packed dna {} with hash {} into {}",
+        dna_def_json.name,
+        dna_file.dna.as_hash(),
+        output.display()
+    );
+
+    Ok(output)
+}
+
+/// Writes the zomes of a packed DNA file to `zomes/<zome name>.wasm`, with a `dna.json` that references them,
+/// into `<dna name>.dna.workdir` next to the file if no output directory is given
+pub async fn unpack_dna_file(dna_file_path: &Path, output: Option<PathBuf>) -> Result<PathBuf> {
+    let dna_file = read_dna_file(&dna_file_path).await?;
+    let dna_def = dna_file.dna.clone();
+
+    let workdir = match output {
+        Some(output) => output,
+        None => dna_file_path
+            .parent()
+            .unwrap_or_else(|| Path::new("."))
+            .join(format!("{}.dna.workdir", dna_def.name)),
+    };
+    let dna_json_path = workdir.join("dna.json");
+    if dna_json_path.exists() {
+        return Err(anyhow!(
+            "{} already exists, remove it or choose another output directory",
+            dna_json_path.display()
+        ));
+    }
+
+    // The zome names come from a DNA file that may have been written by anyone,
+    // and they are used as file names in the workdir
+    for zome_name in dna_def.zomes.iter().map(|(zome_name, _)| &zome_name.0) {
+        check_zome_name(zome_name)?;
+    }
+
+    let zomes_dir = workdir.join("zomes");
+    tokio::fs::create_dir_all(&zomes_dir).await?;
+
+    let mut zomes: BTreeMap<ZomeName, ZomeJson> = BTreeMap::new();
+
+    for (zome_name, _) in dna_def.zomes.iter() {
+        let wasm_zome = dna_def.get_wasm_zome(zome_name)?;
+        let wasm_code = dna_file
+            .code()
+            .get(&wasm_zome.wasm_hash)
+            .ok_or_else(|| anyhow!("The dna file has no wasm for zome {}", zome_name.0))?;
+
+        let wasm_path = zomes_dir.join(format!("{}.wasm", zome_name.0));
+        tokio::fs::write(&wasm_path, wasm_code.code.to_vec()).await?;
+        println!(
+            "Wrote wasm for zome {} to {}",
+            zome_name.0,
+            wasm_path.display()
+        );
+
+        zomes.insert(
+            zome_name.clone(),
+            ZomeJson {
+                wasm_path: format!("./zomes/{}.wasm", zome_name.0),
+                ui_path: None,
+            },
+        );
+    }
+
+    let dna_def_json = DnaDefJson {
+        name: dna_def.name.clone(),
+        uuid: dna_def.uuid.clone(),
+        properties: decode_properties(dna_def.properties.clone())?,
+        zomes,
+        ui_path: None,
+    };
+
+    tokio::fs::write(&dna_json_path, serde_json::to_vec_pretty(&dna_def_json)?).await?;
+    println!("Wrote {}", dna_json_path.display());
+
+    // dna.json lists the zomes sorted by name, so a DNA with its zomes in another order
+    // compiles to a different hash from the workdir
+    let workdir = workdir.canonicalize()?;
+    let unpacked_dna_file = read_dna(&workdir)
+        .await?
+        .compile_dna_file(workdir.clone())
+        .await?;
+    if unpacked_dna_file.dna.as_hash() != dna_file.dna.as_hash() {
+        println!(
+            "Warning: the workdir compiles to dna hash {} instead of {}, as its zomes are sorted by name",
+            unpacked_dna_file.dna.as_hash(),
+            dna_file.dna.as_hash()
+        );
+    }
+
+    Ok(workdir)
+}
@@ -0,0 +1,201 @@
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Instant,
+};
+
+use serde::Serialize;
+
+/// How the progress of long running operations is reported
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProgressMode {
+    /// Human readable lines on stderr
+    Human,
+    /// One JSON event per line on stderr, for CI logs
+    Ndjson,
+    /// No progress at all
+    Quiet,
+}
+
+/// Progress events, as written in `ProgressMode::Ndjson`
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum ProgressEvent<'a> {
+    ZomeStarted {
+        zome: &'a str,
+        index: usize,
+        total: usize,
+    },
+    ChunkUploaded {
+        zome: Option<&'a str>,
+        file: &'a str,
+        chunk: usize,
+        chunks: usize,
+        bytes_sent: usize,
+        bytes_total: usize,
+    },
+    FileUploaded {
+        zome: Option<&'a str>,
+        file: &'a str,
+        file_hash: &'a str,
+    },
+    ZomePublished {
+        zome: &'a str,
+        zome_def_hash: &'a str,
+        unchanged: bool,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct Progress {
+    mode: ProgressMode,
+    start: Instant,
+    zome: Option<String>,
+}
+
+impl Progress {
+    pub fn new(mode: ProgressMode) -> Self {
+        Progress {
+            mode,
+            start: Instant::now(),
+            zome: None,
+        }
+    }
+
+    /// Progress for the uploads of the given zome, sharing the start time with this one
+    pub fn for_zome(&self, zome_name: &str) -> Self {
+        Progress {
+            zome: Some(zome_name.into()),
+            ..self.clone()
+        }
+    }
+
+    pub fn zome_started(&self, zome_name: &str, index: usize, total: usize) {
+        self.report(ProgressEvent::ZomeStarted {
+            zome: zome_name,
+            index: index + 1,
+            total,
+        });
+    }
+
+    pub fn zome_published(&self, zome_name: &str, zome_def_hash: &str, unchanged: bool) {
+        self.report(ProgressEvent::ZomePublished {
+            zome: zome_name,
+            zome_def_hash,
+            unchanged,
+        });
+    }
+
+    pub fn file_uploaded(&self, file_name: &str, file_hash: &str) {
+        self.report(ProgressEvent::FileUploaded {
+            zome: self.zome.as_deref(),
+            file: file_name,
+            file_hash,
+        });
+    }
+
+    fn report(&self, event: ProgressEvent) {
+        let elapsed_secs = self.start.elapsed().as_secs_f64();
+
+        match self.mode {
+            ProgressMode::Quiet => {}
+            ProgressMode::Ndjson => {
+                let mut event_json = serde_json::to_value(&event).unwrap_or_default();
+                event_json["elapsed_secs"] = serde_json::json!(elapsed_secs);
+                eprintln!("{}", event_json);
+            }
+            ProgressMode::Human => {
+                if let Some(message) = human_message(&event) {
+                    eprintln!("[{:>7.1}s] {}", elapsed_secs, message);
+                }
+            }
+        }
+    }
+}
+
+/// Counts the chunks of a file as they finish uploading, possibly concurrently
+pub struct FileProgress<'a> {
+    progress: &'a Progress,
+    file_name: String,
+    chunks_count: usize,
+    bytes_total: usize,
+    chunks_done: AtomicUsize,
+    bytes_done: AtomicUsize,
+}
+
+impl<'a> FileProgress<'a> {
+    pub fn new(
+        progress: &'a Progress,
+        file_name: &str,
+        chunks_count: usize,
+        bytes_total: usize,
+    ) -> Self {
+        FileProgress {
+            progress,
+            file_name: file_name.into(),
+            chunks_count,
+            bytes_total,
+            chunks_done: AtomicUsize::new(0),
+            bytes_done: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn chunk_uploaded(&self, chunk_size: usize) {
+        let chunk = self.chunks_done.fetch_add(1, Ordering::SeqCst) + 1;
+        let bytes_sent = self.bytes_done.fetch_add(chunk_size, Ordering::SeqCst) + chunk_size;
+
+        self.progress.report(ProgressEvent::ChunkUploaded {
+            zome: self.progress.zome.as_deref(),
+            file: &self.file_name,
+            chunk,
+            chunks: self.chunks_count,
+            bytes_sent,
+            bytes_total: self.bytes_total,
+        });
+    }
+}
+
+/// Published zomes are not reported, as they are already printed to stdout
+fn human_message(event: &ProgressEvent) -> Option<String> {
+    match event {
+        ProgressEvent::ZomeStarted { zome, index, total } => {
+            Some(format!("Publishing zome {} ({} of {})", zome, index, total))
+        }
+        ProgressEvent::ChunkUploaded {
+            zome,
+            file,
+            chunk,
+            chunks,
+            bytes_sent,
+            bytes_total,
+        } => Some(format!(
+            "{}{}: chunk {} of {}, {} of {}",
+            zome.map(|z| format!("{} ", z)).unwrap_or_default(),
+            file,
+            chunk,
+            chunks,
+            human_bytes(*bytes_sent),
+            human_bytes(*bytes_total)
+        )),
+        ProgressEvent::FileUploaded {
+            zome,
+            file,
+            file_hash,
+        } => Some(format!(
+            "{}{}: uploaded with hash {}",
+            zome.map(|z| format!("{} ", z)).unwrap_or_default(),
+            file,
+            file_hash
+        )),
+        ProgressEvent::ZomePublished { .. } => None,
+    }
+}
+
+fn human_bytes(bytes: usize) -> String {
+    const MIB: f64 = 1024.0 * 1024.0;
+
+    match bytes as f64 {
+        b if b >= MIB => format!("{:.1} MiB", b / MIB),
+        b if b >= 1024.0 => format!("{:.1} KiB", b / 1024.0),
+        b => format!("{} B", b),
+    }
+}
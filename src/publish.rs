@@ -1,171 +1,220 @@
-use std::convert::TryInto;
-
 use crate::{
-    conductor_api::{
-        app_websocket::AppWebsocket,
-        types::{ClientAppResponse, ClientZomeCall},
-    },
+    client::CompositoryClient,
+    list::{get_all_zome_defs, ZomeDefWithHash},
+    lockfile::{LockedZome, Lockfile},
     types::{DnaTemplate, PublishInstantiatedDnaInput, ZomeReference, ZomeToPublish, ZomeWithCode},
 };
-use anyhow::{anyhow, Result};
-use hc_utils::WrappedEntryHash;
+use anyhow::{Context, Result};
 use holo_hash::HasHash;
-use holochain_types::{dna::DnaFile};
-use holochain_zome_types::CellId;
+use holochain_types::dna::DnaFile;
+
+use self::file_upload::{check_chunk_size, upload_file};
 
-use self::file_upload::upload_file;
+pub use self::dry_run::{
+    dry_run_publish_dna_template, dry_run_publish_instantiated_dna, dry_run_publish_zomes,
+};
+pub use self::file_upload::{parse_chunk_size, parse_jobs, UploadConfig, DEFAULT_CHUNK_SIZE};
+pub use self::upload_state::default_state_dir;
 
+mod dry_run;
 mod file_upload;
+mod upload_state;
 
-pub async fn _publish_dna_template(
-    ws: &mut AppWebsocket,
-    compository_cell_id: &CellId,
+pub async fn publish_dna_template(
+    client: &CompositoryClient,
     dna_name: String,
     zomes: Vec<ZomeWithCode>,
+    lockfile: &mut Lockfile,
+    upload_config: &UploadConfig,
 ) -> Result<String> {
     let names: Vec<String> = zomes.clone().into_iter().map(|z| z.name).collect();
-    let zomes_hashes = publish_zomes(ws, compository_cell_id, zomes).await?;
+    let zomes_hashes = publish_zomes(client, zomes, lockfile, upload_config).await?;
 
     let zome_defs: Vec<ZomeReference> = names
         .into_iter()
-        .enumerate()
-        .map(|(index, name)| ZomeReference {
+        .zip(zomes_hashes.into_iter())
+        .map(|(name, zome_def_hash)| ZomeReference {
             name,
-            zome_def_hash: zomes_hashes[index].clone(),
+            zome_def_hash,
         })
         .collect();
 
+    let dna_template_zome_defs = zome_defs.clone();
     let dna_template = DnaTemplate {
         name: dna_name,
         zome_defs,
     };
 
-    let zome_call = ClientZomeCall {
-        cap: None,
-        cell_id: compository_cell_id.clone(),
-        fn_name: "publish_dna_template".into(),
-        payload: dna_template.try_into()?,
-        provenance: compository_cell_id.agent_pubkey().clone(),
-        zome_name: "compository".into(),
-    };
-
-    let response = ws.call_zome(compository_cell_id, zome_call).await?;
+    let str_hash = client
+        .publish_dna_template(dna_template)
+        .await
+        .context("All zomes were published, but publishing the DNA template failed")?;
 
-    match response {
-        ClientAppResponse::ZomeCall(bytes) => {
-            let hash: WrappedEntryHash = bytes.try_into()?;
-            let str_hash = format!("{}", hash.0);
-            println!("Published template dna with hash {}", str_hash);
-
-            Ok(str_hash)
-        }
-        _ => Err(anyhow!("Bad response")),
+    println!("Published template dna with hash {}", str_hash);
+    for zome_def in dna_template_zome_defs {
+        println!("  {}: {}", zome_def.name, zome_def.zome_def_hash);
     }
+    lockfile.update_dna_template_hash(str_hash.clone());
+
+    Ok(str_hash)
 }
 
 pub async fn publish_zomes(
-    ws: &mut AppWebsocket,
-    compository_cell_id: &CellId,
+    client: &CompositoryClient,
     zomes: Vec<ZomeWithCode>,
+    lockfile: &mut Lockfile,
+    upload_config: &UploadConfig,
 ) -> Result<Vec<String>> {
+    check_chunk_size(client, upload_config.chunk_size)?;
+
+    let total = zomes.len();
     let mut zomes_hashes: Vec<String> = vec![];
 
-    for zome in zomes {
-        let zome_hash = publish_zome(ws, compository_cell_id, zome).await?;
+    let zome_names: Vec<String> = zomes.iter().map(|z| z.name.clone()).collect();
+    lockfile.retain_zomes(&zome_names);
+
+    // Only asked to the conductor if some zome is not already in the lockfile
+    let mut existing_zome_defs: Option<Vec<ZomeDefWithHash>> = None;
+
+    for (index, zome) in zomes.into_iter().enumerate() {
+        let progress = upload_config.progress.for_zome(&zome.name);
+        progress.zome_started(&zome.name, index, total);
+
+        // A zome with the same name and wasm is already published, no need to upload it again
+        let wasm_hash = format!("{}", zome.wasm_hash);
+        if let Some(locked_zome) =
+            lockfile.get_unchanged_zome(&zome.name, &wasm_hash, zome.components_bundle.as_deref())
+        {
+            println!(
+                "Zome {} unchanged, with hash {}",
+                zome.name, locked_zome.zome_def_hash
+            );
+            progress.zome_published(&zome.name, &locked_zome.zome_def_hash, true);
+
+            zomes_hashes.push(locked_zome.zome_def_hash.clone());
+            continue;
+        }
+
+        if existing_zome_defs.is_none() {
+            existing_zome_defs = Some(
+                get_all_zome_defs(client)
+                    .await
+                    .context("Failed to get the already published zomes")?,
+            );
+        }
+        // Published zome defs only record the entry hash of their UI bundle file, not a hash of its
+        // contents, so zomes with UI are always published again in case only their bundle changed
+        let maybe_existing_zome_def = existing_zome_defs.iter().flatten().find(|existing| {
+            existing.zome_def.name == zome.name
+                && existing.zome_def.wasm_hash == zome.wasm_hash
+                && existing.zome_def.components_bundle_file.is_none()
+                && zome.components_bundle.is_none()
+        });
+        if let Some(existing) = maybe_existing_zome_def {
+            println!("Zome {} unchanged, with hash {}", zome.name, existing.hash);
+            progress.zome_published(&zome.name, &existing.hash, true);
+
+            lockfile.update_zome(
+                zome.name.clone(),
+                LockedZome::new(existing.hash.clone(), &existing.zome_def, None),
+            );
+            zomes_hashes.push(existing.hash.clone());
+            continue;
+        }
 
-        zomes_hashes.push(zome_hash);
+        let zome_name = zome.name.clone();
+        let zome_upload_config = UploadConfig {
+            progress,
+            ..upload_config.clone()
+        };
+        let locked_zome = publish_zome(client, zome, &zome_upload_config)
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to publish zome {} ({} of {} zomes were already published)",
+                    zome_name,
+                    zomes_hashes.len(),
+                    total
+                )
+            })?;
+
+        zome_upload_config
+            .progress
+            .zome_published(&zome_name, &locked_zome.zome_def_hash, false);
+        zomes_hashes.push(locked_zome.zome_def_hash.clone());
+        lockfile.update_zome(zome_name, locked_zome);
     }
 
     Ok(zomes_hashes)
 }
 
-pub async fn _publish_insantiated_dna(
-    ws: &mut AppWebsocket,
-    compository_cell_id: &CellId,
+pub async fn publish_instantiated_dna(
+    client: &CompositoryClient,
     dna_file: DnaFile,
     dna_template_hash: String,
-) -> Result<()> {
+    lockfile: &mut Lockfile,
+) -> Result<String> {
     let instantiated_dna_hash = format!("{}", dna_file.dna.as_hash());
+    let uuid = dna_file.dna.uuid.clone();
 
     let input = PublishInstantiatedDnaInput {
-        dna_template_hash,
+        dna_template_hash: dna_template_hash.clone(),
         instantiated_dna_hash: instantiated_dna_hash.clone(),
-        uuid: dna_file.dna.uuid.clone(),
+        uuid: uuid.clone(),
         properties: dna_file.dna.properties.clone(),
     };
-    let zome_call = ClientZomeCall {
-        cap: None,
-        cell_id: compository_cell_id.clone(),
-        fn_name: "publish_instantiated_dna".into(),
-        payload: input.try_into()?,
-        provenance: compository_cell_id.agent_pubkey().clone(),
-        zome_name: "compository".into(),
-    };
-
-    let response = ws.call_zome(compository_cell_id, zome_call).await?;
-
-    match response {
-        ClientAppResponse::ZomeCall(_) => {
-            println!(
-                "Published instantiated dna with hash {}",
-                instantiated_dna_hash
-            );
-
-            Ok(())
-        }
-        _ => Err(anyhow!("Bad response")),
-    }
+    client.publish_instantiated_dna(input).await?;
+
+    println!(
+        "Published instantiated dna with hash {}",
+        instantiated_dna_hash
+    );
+    println!("  template: {}", dna_template_hash);
+    println!("  uuid: {:?}", uuid);
+    // The template may have been given with `-t` instead of published in this run
+    lockfile.update_dna_template_hash(dna_template_hash);
+    lockfile.instantiated_dna_hash = Some(instantiated_dna_hash.clone());
+
+    Ok(instantiated_dna_hash)
 }
 
 /** Helper functions */
 
 async fn publish_zome(
-    ws: &mut AppWebsocket,
-    compository_cell_id: &CellId,
+    client: &CompositoryClient,
     zome: ZomeWithCode,
-) -> Result<String> {
-    let zome_to_publish = upload_zome(ws, compository_cell_id, zome).await?;
-
-    let zome_call = ClientZomeCall {
-        cap: None,
-        cell_id: compository_cell_id.clone(),
-        fn_name: "publish_zome".into(),
-        payload: zome_to_publish.try_into()?,
-        provenance: compository_cell_id.agent_pubkey().clone(),
-        zome_name: "compository".into(),
-    };
-
-    let response = ws.call_zome(compository_cell_id, zome_call).await?;
-
-    match response {
-        ClientAppResponse::ZomeCall(bytes) => {
-            let hash: WrappedEntryHash = bytes.try_into()?;
-            let str_hash = format!("{}", hash.0);
-
-            println!("Published zome with hash {}", str_hash);
-
-            Ok(str_hash)
-        }
-        _ => Err(anyhow!("Bad response")),
-    }
+    upload_config: &UploadConfig,
+) -> Result<LockedZome> {
+    let zome_name = zome.name.clone();
+    let components_bundle = zome.components_bundle.clone();
+    let zome_to_publish = upload_zome(client, zome, upload_config).await?;
+    let str_hash = client.publish_zome(zome_to_publish.clone()).await?;
+
+    println!("Published zome {} with hash {}", zome_name, str_hash);
+
+    Ok(LockedZome::new(
+        str_hash,
+        &zome_to_publish,
+        components_bundle.as_deref(),
+    ))
 }
 
 async fn upload_zome(
-    ws: &mut AppWebsocket,
-    compository_cell_id: &CellId,
+    client: &CompositoryClient,
     zome: ZomeWithCode,
+    upload_config: &UploadConfig,
 ) -> Result<ZomeToPublish> {
     let file_hash = upload_file(
-        ws,
-        compository_cell_id,
+        client,
         "test.wasm".into(),
         "wasm".into(),
         &zome.wasm_code.code.to_vec(),
+        upload_config,
     )
     .await?;
 
     let components_bundle_file = match zome.components_bundle {
-        Some(bundle) => Some(upload_components_bundle(ws, compository_cell_id, bundle).await?),
+        Some(bundle) => Some(upload_components_bundle(client, bundle, upload_config).await?),
         None => None,
     };
 
@@ -183,16 +232,16 @@ async fn upload_zome(
 }
 
 async fn upload_components_bundle(
-    ws: &mut AppWebsocket,
-    compository_cell_id: &CellId,
+    client: &CompositoryClient,
     bundle_contents: Vec<u8>,
+    upload_config: &UploadConfig,
 ) -> Result<String> {
     let file_hash = upload_file(
-        ws,
-        compository_cell_id,
+        client,
         "bundle.js".into(),
         "js".into(),
         &bundle_contents,
+        upload_config,
     )
     .await?;
 
@@ -0,0 +1,155 @@
+use std::convert::TryInto;
+
+use anyhow::Result;
+use holo_hash::HasHash;
+use holochain_serialized_bytes::prelude::*;
+use holochain_types::dna::DnaFile;
+
+use crate::types::{
+    Chunk, DnaTemplate, PublishInstantiatedDnaInput, ZomeReference, ZomeToPublish, ZomeWithCode,
+};
+
+use super::file_upload::{file_metadata_input, UploadConfig};
+
+/// Stands in for the entry hashes the conductor would return, with the same length as a real one
+const PLACEHOLDER_HASH: &str = "uhCEkXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXX";
+
+/// Prints the zome calls that `publish_zomes` would make, without connecting to any conductor
+pub fn dry_run_publish_zomes(
+    zomes: Vec<ZomeWithCode>,
+    upload_config: &UploadConfig,
+) -> Result<Vec<String>> {
+    let mut zomes_hashes: Vec<String> = vec![];
+
+    for zome in zomes {
+        println!("Zome {}", zome.name);
+
+        let wasm_file =
+            dry_run_upload_file("test.wasm", "wasm", &zome.wasm_code.code, upload_config)?;
+        let components_bundle_file = match &zome.components_bundle {
+            Some(bundle) => Some(dry_run_upload_file(
+                "bundle.js",
+                "js",
+                bundle,
+                upload_config,
+            )?),
+            None => None,
+        };
+
+        let zome_to_publish = ZomeToPublish {
+            name: zome.name,
+            components_bundle_file,
+            entry_defs: zome.entry_defs,
+            required_membrane_proof: zome.required_membrane_proof,
+            required_properties: zome.required_properties,
+            wasm_file,
+            wasm_hash: zome.wasm_hash,
+        };
+
+        print_call("compository", "publish_zome", &zome_to_publish.try_into()?);
+
+        zomes_hashes.push(PLACEHOLDER_HASH.into());
+    }
+
+    Ok(zomes_hashes)
+}
+
+/// Prints the zome calls that `publish_dna_template` would make, without connecting to any conductor
+pub fn dry_run_publish_dna_template(
+    dna_name: String,
+    zomes: Vec<ZomeWithCode>,
+    upload_config: &UploadConfig,
+) -> Result<String> {
+    let names: Vec<String> = zomes.iter().map(|z| z.name.clone()).collect();
+    let zomes_hashes = dry_run_publish_zomes(zomes, upload_config)?;
+
+    let dna_template = DnaTemplate {
+        name: dna_name,
+        zome_defs: names
+            .into_iter()
+            .zip(zomes_hashes.into_iter())
+            .map(|(name, zome_def_hash)| ZomeReference {
+                name,
+                zome_def_hash,
+            })
+            .collect(),
+    };
+
+    println!("DNA template {}", dna_template.name);
+    print_call(
+        "compository",
+        "publish_dna_template",
+        &dna_template.try_into()?,
+    );
+
+    Ok(PLACEHOLDER_HASH.into())
+}
+
+/// Prints the zome call that `publish_instantiated_dna` would make, without connecting to any conductor
+pub fn dry_run_publish_instantiated_dna(
+    dna_file: DnaFile,
+    dna_template_hash: String,
+) -> Result<()> {
+    let instantiated_dna_hash = format!("{}", dna_file.dna.as_hash());
+
+    let input = PublishInstantiatedDnaInput {
+        dna_template_hash,
+        instantiated_dna_hash: instantiated_dna_hash.clone(),
+        uuid: dna_file.dna.uuid.clone(),
+        properties: dna_file.dna.properties.clone(),
+    };
+
+    println!("Instantiated DNA {}", instantiated_dna_hash);
+    print_call(
+        "compository",
+        "publish_instantiated_dna",
+        &input.try_into()?,
+    );
+
+    Ok(())
+}
+
+/** Helper functions */
+
+fn dry_run_upload_file(
+    name: &str,
+    file_type: &str,
+    content: &[u8],
+    upload_config: &UploadConfig,
+) -> Result<String> {
+    let mut chunks_hashes: Vec<String> = vec![];
+    let mut chunks_bytes = 0;
+
+    for chunk in content.chunks(upload_config.chunk_size) {
+        let payload: SerializedBytes = Chunk(chunk.to_vec()).try_into()?;
+        chunks_bytes += payload.bytes().len();
+
+        chunks_hashes.push(PLACEHOLDER_HASH.into());
+    }
+
+    println!(
+        "  file_storage/create_file_chunk: {} chunks for {} ({} bytes of payload)",
+        chunks_hashes.len(),
+        name,
+        chunks_bytes
+    );
+
+    let metadata =
+        file_metadata_input(name.into(), file_type.into(), content.len(), chunks_hashes)?;
+    print_call(
+        "file_storage",
+        "create_file_metadata",
+        &metadata.try_into()?,
+    );
+
+    Ok(PLACEHOLDER_HASH.into())
+}
+
+fn print_call(zome_name: &str, fn_name: &str, payload: &SerializedBytes) {
+    println!(
+        "  {}/{}: {} bytes of payload",
+        zome_name,
+        fn_name,
+        payload.bytes().len()
+    );
+}
@@ -1,94 +1,170 @@
 use holochain_serialized_bytes::prelude::*;
-use std::{convert::TryInto, time::SystemTime};
+use std::{convert::TryInto, path::PathBuf, time::SystemTime};
 
 use anyhow::{anyhow, Result};
-use hc_utils::WrappedEntryHash;
-use holochain_zome_types::{timestamp, CellId};
-
-use crate::conductor_api::{
-    app_websocket::AppWebsocket,
-    types::{ClientAppResponse, ClientZomeCall},
+use futures::{StreamExt, TryStreamExt};
+use holochain_zome_types::timestamp;
+use tokio::sync::Mutex;
+
+use crate::{
+    client::CompositoryClient,
+    conductor_api::types::ClientAppRequest,
+    progress::{FileProgress, Progress},
+    types::{Chunk, CreateFileMetadataInput},
 };
 
-const CHUNKS_SIZE: usize = 1024 * 1024 * 10;
+use super::upload_state::UploadState;
+
+/// Small enough for the worst case size of its message to fit in the default 16 MiB websocket frames
+pub const DEFAULT_CHUNK_SIZE: usize = 1024 * 1024 * 4;
+
+/// Room left in every message for the envelope the websocket adds around the request
+const MESSAGE_ENVELOPE_SIZE: usize = 1024;
+
+#[derive(Debug, Clone)]
+pub struct UploadConfig {
+    /// Maximum number of chunks being uploaded at the same time
+    pub jobs: usize,
+    /// Size in bytes of the chunks the files are split into
+    pub chunk_size: usize,
+    /// Directory where the progress of the uploads is saved, to resume them if they fail
+    pub state_dir: PathBuf,
+    /// Where the progress of the uploads is reported
+    pub progress: Progress,
+}
 
 pub async fn upload_file(
-    ws: &mut AppWebsocket,
-    compository_cell_id: &CellId,
+    client: &CompositoryClient,
     name: String,
     file_type: String,
     content: &[u8],
+    upload_config: &UploadConfig,
 ) -> Result<String> {
     let size = content.len();
-    let chunk_iter = content.chunks(CHUNKS_SIZE);
+    let chunk_iter = content.chunks(upload_config.chunk_size);
+    let chunks_count = chunk_iter.len();
+
+    let mut upload_state = UploadState::load(
+        &upload_config.state_dir,
+        client.cell_id(),
+        content,
+        upload_config.chunk_size,
+    )
+    .await?;
+
+    if upload_state.uploaded_chunks_count() > 0 {
+        println!(
+            "Resuming upload of {}: {} of {} chunks were already uploaded",
+            name,
+            upload_state.uploaded_chunks_count(),
+            chunks_count
+        );
+    }
 
-    let mut chunk_hashes: Vec<String> = vec![];
+    let upload_state = Mutex::new(upload_state);
+    let file_progress = FileProgress::new(&upload_config.progress, &name, chunks_count, size);
 
-    for chunk in chunk_iter {
-        let hash = upload_chunk(ws, &compository_cell_id, chunk).await?;
+    // The websocket multiplexes requests, so up to `--jobs` chunks are in flight at the same time.
+    // `buffered` yields the hashes in the original chunk order
+    let chunk_hashes: Vec<String> = futures::stream::iter(chunk_iter.enumerate())
+        .map(|(index, chunk)| {
+            let upload_state = &upload_state;
+            let file_progress = &file_progress;
+            async move {
+                let chunk_hash = upload_chunk_resumable(client, upload_state, index, chunk).await?;
+                file_progress.chunk_uploaded(chunk.len());
 
-        chunk_hashes.push(hash);
-    }
+                Ok::<String, anyhow::Error>(chunk_hash)
+            }
+        })
+        .buffered(upload_config.jobs)
+        .try_collect()
+        .await?;
 
-    let file_hash = create_file(
-        ws,
-        &compository_cell_id,
-        name,
-        file_type,
-        size,
-        chunk_hashes,
-    )
-    .await?;
+    let upload_state = upload_state.into_inner();
+
+    let file_metadata = file_metadata_input(name.clone(), file_type, size, chunk_hashes)?;
+    let file_hash = client.create_file_metadata(file_metadata).await?;
+
+    upload_state.remove().await?;
+    upload_config.progress.file_uploaded(&name, &file_hash);
 
     Ok(file_hash)
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, SerializedBytes)]
-struct Chunk(Vec<u8>);
+/// Parses the `--chunk-size` argument, which must be bigger than 0 to split the files at all
+pub fn parse_chunk_size(chunk_size: &str) -> Result<usize> {
+    match chunk_size.parse::<usize>() {
+        Ok(0) => Err(anyhow!("The chunk size must be bigger than 0")),
+        Ok(chunk_size) => Ok(chunk_size),
+        Err(error) => Err(anyhow!("Invalid chunk size {}: {}", chunk_size, error)),
+    }
+}
+
+/// Parses the `--jobs` argument, which must be bigger than 0 for any chunk to be uploaded
+pub fn parse_jobs(jobs: &str) -> Result<usize> {
+    match jobs.parse::<usize>() {
+        Ok(0) => Err(anyhow!("The number of jobs must be bigger than 0")),
+        Ok(jobs) => Ok(jobs),
+        Err(error) => Err(anyhow!("Invalid number of jobs {}: {}", jobs, error)),
+    }
+}
+
+/// Checks that a `create_file_chunk` call with a full chunk fits in a websocket message
+pub fn check_chunk_size(client: &CompositoryClient, chunk_size: usize) -> Result<()> {
+    // Chunks are serialized as arrays of integers, where the bytes of 0x80 or more take 2 bytes,
+    // so the worst case is budgeted. The overhead of the request is measured with a 1 byte chunk
+    // instead of allocating a full one, and the longer length prefix of bigger chunks fits in
+    // the envelope room
+    let request = ClientAppRequest::ZomeCall(client.zome_call(
+        "file_storage",
+        "create_file_chunk",
+        Chunk(vec![0xff]).try_into()?,
+    ));
+    let request_bytes: SerializedBytes = request.try_into()?;
+    let request_overhead = request_bytes.bytes().len() - 2;
+    let message_size = 2 * chunk_size + request_overhead + MESSAGE_ENVELOPE_SIZE;
+
+    if message_size > client.max_message_size() {
+        return Err(anyhow!(
+            "Chunks of {} bytes need messages of up to {} bytes, but the websocket only accepts messages of up to {} bytes: use a smaller --chunk-size",
+            chunk_size,
+            message_size,
+            client.max_message_size()
+        ));
+    }
 
-async fn upload_chunk(
-    ws: &mut AppWebsocket,
-    compository_cell_id: &CellId,
+    Ok(())
+}
+
+/// Uploads the chunk, unless it was already uploaded in a previous attempt
+async fn upload_chunk_resumable(
+    client: &CompositoryClient,
+    upload_state: &Mutex<UploadState>,
+    index: usize,
     code: &[u8],
 ) -> Result<String> {
-    let zome_call = ClientZomeCall {
-        cap: None,
-        cell_id: compository_cell_id.clone(),
-        fn_name: "create_file_chunk".into(),
-        payload: Chunk(code.to_vec()).try_into()?,
-        provenance: compository_cell_id.agent_pubkey().clone(),
-        zome_name: "file_storage".into(),
-    };
-    let response = ws.call_zome(compository_cell_id, zome_call).await?;
-
-    match response {
-        ClientAppResponse::ZomeCall(bytes) => {
-            let hash: WrappedEntryHash = bytes.try_into()?;
-
-            Ok(format!("{}", hash.0))
-        }
-        _ => Err(anyhow!("Bad response")),
+    if let Some(chunk_hash) = upload_state.lock().await.chunk_hash(index) {
+        return Ok(chunk_hash);
     }
-}
 
-#[derive(Clone, Serialize, Deserialize, Debug, SerializedBytes)]
-#[serde(rename_all = "camelCase")]
-pub struct CreateFileMetadataInput {
-    pub name: String,
-    pub last_modified: timestamp::Timestamp,
-    pub size: usize,
-    pub file_type: String,
-    pub chunks_hashes: Vec<String>,
+    let chunk_hash = client.create_file_chunk(Chunk(code.to_vec())).await?;
+
+    upload_state
+        .lock()
+        .await
+        .set_chunk_hash(index, chunk_hash.clone())
+        .await?;
+
+    Ok(chunk_hash)
 }
 
-async fn create_file(
-    ws: &mut AppWebsocket,
-    compository_cell_id: &CellId,
+pub fn file_metadata_input(
     name: String,
     file_type: String,
     size: usize,
     chunks_hashes: Vec<String>,
-) -> Result<String> {
+) -> Result<CreateFileMetadataInput> {
     let start = SystemTime::now();
     let since_the_epoch = start.duration_since(SystemTime::UNIX_EPOCH)?;
 
@@ -97,31 +173,11 @@ async fn create_file(
         since_the_epoch.subsec_nanos(),
     );
 
-    let payload = CreateFileMetadataInput {
+    Ok(CreateFileMetadataInput {
         name,
         last_modified: timestamp,
         size,
         chunks_hashes,
         file_type,
-    };
-
-    let zome_call = ClientZomeCall {
-        cap: None,
-        cell_id: compository_cell_id.clone(),
-        fn_name: "create_file_metadata".into(),
-        payload: payload.try_into()?,
-        provenance: compository_cell_id.agent_pubkey().clone(),
-        zome_name: "file_storage".into(),
-    };
-
-    let response = ws.call_zome(compository_cell_id, zome_call).await?;
-
-    match response {
-        ClientAppResponse::ZomeCall(bytes) => {
-            let hash: WrappedEntryHash = bytes.try_into()?;
-
-            Ok(format!("{}", hash.0))
-        }
-        _ => Err(anyhow!("Bad response")),
-    }
+    })
 }
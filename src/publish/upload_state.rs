@@ -0,0 +1,171 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use holochain_zome_types::CellId;
+use serde::{Deserialize, Serialize};
+
+/// Hashes of the chunks of a file that have already been uploaded to a compository,
+/// persisted on disk so that an interrupted upload can be resumed.
+/// It's removed once the file is created, as its metadata may not survive a reset of the conductor
+#[derive(Debug)]
+pub struct UploadState {
+    path: PathBuf,
+    data: UploadStateData,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct UploadStateData {
+    chunks_hashes: BTreeMap<usize, String>, // Indexed by position of the chunk in the file
+}
+
+impl UploadState {
+    /// Loads the upload state for the given file contents, keyed by the compository cell
+    /// uploading them, which includes the agent, the hash of the contents and the chunk size
+    pub async fn load(
+        state_dir: &Path,
+        compository_cell_id: &CellId,
+        content: &[u8],
+        chunk_size: usize,
+    ) -> Result<UploadState> {
+        let cell_dir = PathBuf::from(format!("{}", compository_cell_id.dna_hash()))
+            .join(format!("{}", compository_cell_id.agent_pubkey()));
+
+        UploadState::load_from_path(state_path(state_dir, &cell_dir, content, chunk_size)).await
+    }
+
+    async fn load_from_path(path: PathBuf) -> Result<UploadState> {
+        let data = match path.exists() {
+            true => {
+                let state_data = tokio::fs::read(&path).await?;
+                serde_json::from_slice(&state_data)
+                    .with_context(|| format!("Invalid upload state {}", path.display()))?
+            }
+            false => UploadStateData::default(),
+        };
+
+        Ok(UploadState { path, data })
+    }
+
+    pub fn chunk_hash(&self, index: usize) -> Option<String> {
+        self.data.chunks_hashes.get(&index).cloned()
+    }
+
+    pub fn uploaded_chunks_count(&self) -> usize {
+        self.data.chunks_hashes.len()
+    }
+
+    pub async fn set_chunk_hash(&mut self, index: usize, chunk_hash: String) -> Result<()> {
+        self.data.chunks_hashes.insert(index, chunk_hash);
+        self.save().await
+    }
+
+    /// Deletes the state from disk, once the file has been created with all its chunks
+    pub async fn remove(self) -> Result<()> {
+        if self.path.exists() {
+            tokio::fs::remove_file(&self.path).await.with_context(|| {
+                format!("Failed to remove upload state {}", self.path.display())
+            })?;
+        }
+
+        Ok(())
+    }
+
+    async fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        let state_data = serde_json::to_vec(&self.data)?;
+        tokio::fs::write(&self.path, state_data)
+            .await
+            .with_context(|| format!("Failed to write upload state {}", self.path.display()))?;
+
+        Ok(())
+    }
+}
+
+fn state_path(state_dir: &Path, cell_dir: &Path, content: &[u8], chunk_size: usize) -> PathBuf {
+    let content_hash = blake2b_simd::blake2b(content).to_hex();
+
+    state_dir.join("uploads").join(cell_dir).join(format!(
+        "{}-{}.json",
+        content_hash.as_str(),
+        chunk_size
+    ))
+}
+
+/// `$XDG_DATA_HOME/compository` or its platform equivalent, falling back to `./.compository`
+pub fn default_state_dir() -> PathBuf {
+    match directories::ProjectDirs::from("", "", "compository") {
+        Some(project_dirs) => project_dirs.data_dir().to_path_buf(),
+        None => PathBuf::from(".compository"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_state_path(test_name: &str, content: &[u8], chunk_size: usize) -> PathBuf {
+        let state_dir = std::env::temp_dir().join(format!(
+            "compository-upload-state-{}-{}",
+            test_name,
+            std::process::id()
+        ));
+
+        state_path(&state_dir, Path::new("dna/agent"), content, chunk_size)
+    }
+
+    #[tokio::test]
+    async fn saved_chunks_are_loaded_again() {
+        let path = test_state_path("round-trip", b"file contents", 4);
+
+        let mut upload_state = UploadState::load_from_path(path.clone()).await.unwrap();
+        assert_eq!(upload_state.uploaded_chunks_count(), 0);
+
+        upload_state
+            .set_chunk_hash(0, "hash0".into())
+            .await
+            .unwrap();
+        upload_state
+            .set_chunk_hash(2, "hash2".into())
+            .await
+            .unwrap();
+
+        let upload_state = UploadState::load_from_path(path).await.unwrap();
+        assert_eq!(upload_state.uploaded_chunks_count(), 2);
+        assert_eq!(upload_state.chunk_hash(0), Some("hash0".into()));
+        assert_eq!(upload_state.chunk_hash(1), None);
+        assert_eq!(upload_state.chunk_hash(2), Some("hash2".into()));
+
+        upload_state.remove().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn removed_state_starts_from_scratch() {
+        let path = test_state_path("remove", b"file contents", 4);
+
+        let mut upload_state = UploadState::load_from_path(path.clone()).await.unwrap();
+        upload_state
+            .set_chunk_hash(0, "hash0".into())
+            .await
+            .unwrap();
+        upload_state.remove().await.unwrap();
+
+        assert!(!path.exists());
+        let upload_state = UploadState::load_from_path(path).await.unwrap();
+        assert_eq!(upload_state.uploaded_chunks_count(), 0);
+    }
+
+    #[test]
+    fn state_is_keyed_by_content_and_chunk_size() {
+        let path = test_state_path("key", b"file contents", 4);
+
+        assert_eq!(path, test_state_path("key", b"file contents", 4));
+        assert_ne!(path, test_state_path("key", b"file contents", 8));
+        assert_ne!(path, test_state_path("key", b"other contents", 4));
+    }
+}
@@ -0,0 +1,133 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use holochain_types::dna::DnaFile;
+use holochain_zome_types::CellId;
+
+use crate::{
+    cell::get_compository_cell_id,
+    conductor_api::{admin_websocket::AdminWebsocket, app_websocket::AppWebsocket},
+};
+
+/// Nick of the compository cell inside its installed app
+const COMPOSITORY_CELL_NICK: &str = "compository";
+
+/// Installs and activates the compository DNA, unless it's already installed,
+/// and attaches an App interface on the given port to talk to it
+pub async fn setup_compository(
+    admin_ws: &mut AdminWebsocket,
+    dna_path: &Path,
+    installed_app_id: String,
+    app_port: u16,
+) -> Result<CellId> {
+    let dna_path = dna_path
+        .canonicalize()
+        .with_context(|| format!("Could not find the DNA file {}", dna_path.display()))?;
+    let content = tokio::fs::read(&dna_path).await?;
+    let dna_file = DnaFile::from_file_content(&content)
+        .await
+        .with_context(|| format!("{} is not a valid DNA file", dna_path.display()))?;
+    let dna_hash = dna_file.dna_hash().clone();
+
+    let already_installed = match admin_ws.list_dnas().await?.contains(&dna_hash) {
+        true => admin_ws
+            .list_cell_ids()
+            .await?
+            .iter()
+            .any(|cell_id| cell_id.dna_hash() == &dna_hash),
+        false => false,
+    };
+
+    if already_installed {
+        println!("The compository dna {} was already installed", dna_hash);
+
+        let active_app_ids = admin_ws.list_active_app_ids().await?;
+        if !active_app_ids.contains(&installed_app_id) {
+            admin_ws
+                .activate_app(installed_app_id.clone())
+                .await
+                .with_context(|| {
+                    format!(
+                        "Could not activate app {}: pass the id of the app that contains the compository dna with -i",
+                        installed_app_id
+                    )
+                })?;
+            println!("Activated app {}", installed_app_id);
+        }
+    } else {
+        let agent_key = admin_ws.generate_agent_pub_key().await?;
+        println!("Generated agent {}", agent_key);
+
+        admin_ws
+            .install_app_from_path(
+                installed_app_id.clone(),
+                agent_key,
+                COMPOSITORY_CELL_NICK.into(),
+                dna_path,
+            )
+            .await?;
+        println!(
+            "Installed the compository dna {} as app {}",
+            dna_hash, installed_app_id
+        );
+
+        admin_ws.activate_app(installed_app_id.clone()).await?;
+        println!("Activated app {}", installed_app_id);
+    }
+
+    let port = attach_app_interface(admin_ws, app_port, &installed_app_id).await?;
+
+    // The dna may have been installed in another app by a previous setup or by hand,
+    // so the cell is looked up in the app through the interface that the CLI will use
+    let mut app_ws =
+        AppWebsocket::connect(admin_ws.app_interface_url(port), admin_ws.request_config()).await?;
+    let cell_id = get_compository_cell_id(&mut app_ws, installed_app_id.clone(), Some(dna_hash))
+        .await
+        .with_context(|| {
+            format!(
+                "App {} doesn't contain the compository dna: pass the id of the app that contains it with -i",
+                installed_app_id
+            )
+        })?;
+
+    println!();
+    println!("The compository is ready, publish into it with:");
+    println!(
+        "  compository publish-zomes -c {} -i {} -u {} -w <workdir>",
+        cell_id.dna_hash(),
+        installed_app_id,
+        admin_ws.app_interface_url(port)
+    );
+
+    Ok(cell_id)
+}
+
+/// Attaches an App interface on the port, unless the conductor already serves one there,
+/// as it does when the setup is run again
+async fn attach_app_interface(
+    admin_ws: &mut AdminWebsocket,
+    app_port: u16,
+    installed_app_id: &str,
+) -> Result<u16> {
+    let attach_error = match admin_ws.attach_app_interface(Some(app_port)).await {
+        Ok(port) => {
+            println!("Attached an app interface on port {}", port);
+            return Ok(port);
+        }
+        Err(error) => error,
+    };
+
+    // The port may be taken by anything else, so it's only reused if it answers app requests
+    let url = admin_ws.app_interface_url(app_port);
+    let mut app_ws = match AppWebsocket::connect(url, admin_ws.request_config()).await {
+        Ok(app_ws) => app_ws,
+        Err(_) => return Err(attach_error),
+    };
+    match app_ws.app_info(installed_app_id.into()).await {
+        Ok(_) => {
+            println!("An app interface was already attached on port {}", app_port);
+            Ok(app_port)
+        }
+        Err(_) => Err(attach_error),
+    }
+}
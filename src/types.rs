@@ -1,6 +1,8 @@
+use hc_utils::WrappedEntryHash;
 use holo_hash::WasmHash;
 use holochain_serialized_bytes::prelude::*;
 use holochain_types::dna::wasm::DnaWasm;
+use holochain_zome_types::timestamp::Timestamp;
 
 #[derive(Debug, Clone)]
 pub struct ZomeWithCode {
@@ -36,6 +38,26 @@ pub struct DnaTemplate {
     pub zome_defs: Vec<ZomeReference>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, SerializedBytes)]
+pub struct GetAllZomeDefsOutput(pub Vec<(WrappedEntryHash, ZomeToPublish)>);
+
+#[derive(Debug, Clone, Serialize, Deserialize, SerializedBytes)]
+pub struct GetAllDnaTemplatesOutput(pub Vec<(WrappedEntryHash, DnaTemplate)>);
+
+/// Signals emitted by the compository zome when something is published
+#[derive(Debug, Clone, Serialize, Deserialize, SerializedBytes)]
+#[serde(tag = "type", content = "payload", rename_all = "snake_case")]
+pub enum CompositorySignal {
+    ZomePublished {
+        zome_def_hash: WrappedEntryHash,
+        zome_def: ZomeToPublish,
+    },
+    DnaTemplatePublished {
+        dna_template_hash: WrappedEntryHash,
+        dna_template: DnaTemplate,
+    },
+}
+
 #[derive(Debug, Serialize, SerializedBytes, Deserialize, Clone)]
 pub struct PublishInstantiatedDnaInput {
     pub dna_template_hash: String,
@@ -43,3 +65,27 @@ pub struct PublishInstantiatedDnaInput {
     pub uuid: String,
     pub properties: SerializedBytes, // TODO: fix this
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize, SerializedBytes)]
+pub struct Chunk(pub Vec<u8>);
+
+#[derive(Clone, Serialize, Deserialize, Debug, SerializedBytes)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateFileMetadataInput {
+    pub name: String,
+    pub last_modified: Timestamp,
+    pub size: usize,
+    pub file_type: String,
+    pub chunks_hashes: Vec<String>,
+}
+
+/// File metadata as stored by the `file_storage` zome
+#[derive(Clone, Serialize, Deserialize, Debug, SerializedBytes)]
+#[serde(rename_all = "camelCase")]
+pub struct FileMetadata {
+    pub name: String,
+    pub last_modified: Timestamp,
+    pub size: usize,
+    pub file_type: String,
+    pub chunks_hashes: Vec<String>,
+}
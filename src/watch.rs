@@ -0,0 +1,160 @@
+use std::convert::TryFrom;
+
+use anyhow::Result;
+use serde_json::json;
+use tracing::trace;
+
+use crate::{client::CompositoryClient, types::CompositorySignal};
+
+/// Prints the zomes and DNA templates published in the compository as its signals arrive
+pub async fn watch_publications(client: &CompositoryClient, as_json: bool) -> Result<()> {
+    let mut signals = client.subscribe_signals();
+
+    if !as_json {
+        println!("Watching new publications, press Ctrl+C to stop");
+    }
+
+    loop {
+        let (cell_id, payload) = signals.recv().await?;
+        if &cell_id != client.cell_id() {
+            continue;
+        }
+
+        let signal = match CompositorySignal::try_from(payload) {
+            Ok(signal) => signal,
+            Err(error) => {
+                trace!("ignoring unknown compository signal: {:?}", error);
+                continue;
+            }
+        };
+
+        println!("{}", format_signal(signal, as_json));
+    }
+}
+
+/// Formats the signal as a line of text, or as a single line JSON object for `--json`
+fn format_signal(signal: CompositorySignal, as_json: bool) -> String {
+    match (signal, as_json) {
+        (
+            CompositorySignal::ZomePublished {
+                zome_def_hash,
+                zome_def,
+            },
+            true,
+        ) => json!({
+            "type": "zome_published",
+            "name": zome_def.name,
+            "hash": format!("{}", zome_def_hash.0),
+            "wasm_hash": format!("{}", zome_def.wasm_hash),
+            "entry_defs": zome_def.entry_defs,
+            "has_ui": zome_def.components_bundle_file.is_some(),
+        })
+        .to_string(),
+        (
+            CompositorySignal::ZomePublished {
+                zome_def_hash,
+                zome_def,
+            },
+            false,
+        ) => format!(
+            "New zome {} with hash {} (entry defs: [{}])",
+            zome_def.name,
+            zome_def_hash.0,
+            zome_def.entry_defs.join(", ")
+        ),
+        (
+            CompositorySignal::DnaTemplatePublished {
+                dna_template_hash,
+                dna_template,
+            },
+            true,
+        ) => json!({
+            "type": "dna_template_published",
+            "name": dna_template.name,
+            "hash": format!("{}", dna_template_hash.0),
+            "zome_defs": dna_template.zome_defs,
+        })
+        .to_string(),
+        (
+            CompositorySignal::DnaTemplatePublished {
+                dna_template_hash,
+                dna_template,
+            },
+            false,
+        ) => {
+            let zome_names: Vec<String> = dna_template
+                .zome_defs
+                .iter()
+                .map(|z| z.name.clone())
+                .collect();
+
+            format!(
+                "New DNA template {} with hash {} (zomes: [{}])",
+                dna_template.name,
+                dna_template_hash.0,
+                zome_names.join(", ")
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hc_utils::WrappedEntryHash;
+    use holo_hash::{EntryHash, WasmHash};
+
+    use super::*;
+    use crate::types::{DnaTemplate, ZomeReference, ZomeToPublish};
+
+    fn zome_published() -> CompositorySignal {
+        CompositorySignal::ZomePublished {
+            zome_def_hash: WrappedEntryHash(EntryHash::from_raw_32(vec![1; 32])),
+            zome_def: ZomeToPublish {
+                name: "todos".into(),
+                wasm_file: "uhCEk".into(),
+                components_bundle_file: None,
+                wasm_hash: WasmHash::from_raw_32(vec![2; 32]),
+                entry_defs: vec!["todo".into(), "list".into()],
+                required_properties: vec![],
+                required_membrane_proof: false,
+            },
+        }
+    }
+
+    fn dna_template_published() -> CompositorySignal {
+        CompositorySignal::DnaTemplatePublished {
+            dna_template_hash: WrappedEntryHash(EntryHash::from_raw_32(vec![3; 32])),
+            dna_template: DnaTemplate {
+                name: "todo-app".into(),
+                zome_defs: vec![ZomeReference {
+                    name: "todos".into(),
+                    zome_def_hash: "uhCEk".into(),
+                }],
+            },
+        }
+    }
+
+    #[test]
+    fn json_signals_are_single_json_lines() {
+        for signal in vec![zome_published(), dna_template_published()] {
+            let line = format_signal(signal, true);
+
+            assert!(!line.contains('\n'));
+            let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+            assert!(value.is_object());
+        }
+    }
+
+    #[test]
+    fn json_signals_have_their_type() {
+        let zome_line: serde_json::Value =
+            serde_json::from_str(&format_signal(zome_published(), true)).unwrap();
+        let template_line: serde_json::Value =
+            serde_json::from_str(&format_signal(dna_template_published(), true)).unwrap();
+
+        assert_eq!(zome_line["type"], "zome_published");
+        assert_eq!(zome_line["name"], "todos");
+        assert_eq!(template_line["type"], "dna_template_published");
+        assert_eq!(template_line["name"], "todo-app");
+    }
+}